@@ -0,0 +1,69 @@
+// Plugs a material of our own into the renderer and prints the result to the
+// terminal, without touching the file system.
+//
+//     cargo run --release --example custom_material
+
+use std::sync::Arc;
+
+use ray_tracing_in_one_weekend::common::vec3_opts::random_unit_vector;
+use ray_tracing_in_one_weekend::integrator::path::PathIntegrator;
+use ray_tracing_in_one_weekend::one_week::material::Lambertian;
+use ray_tracing_in_one_weekend::one_week::sphere::Sphere;
+use ray_tracing_in_one_weekend::{
+    Background, Camera, CameraFrame, Color, HitRecord, HittableList, LightList, Material, Perspective, Point3, Ray,
+    Renderer, Scene, Vec3,
+};
+
+// Diffuse stripes of two colours, `width` apart along x.
+struct Stripes {
+    a: Color,
+    b: Color,
+    width: f64,
+}
+
+impl Material for Stripes {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let albedo = if (rec.p.x / self.width).floor() as i64 % 2 == 0 { self.a } else { self.b };
+        let mut direction = rec.shading_normal + random_unit_vector();
+        if direction.near_zero() {
+            direction = rec.shading_normal;
+        }
+        Some((albedo, Ray::new(&rec.p, &direction).with_time(r_in.time)))
+    }
+}
+
+fn main () {
+    let mut objects = HittableList { objects: Vec::new() };
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.4, 0.4, 0.4) }),
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Stripes { a: Color::new(0.9, 0.9, 0.9), b: Color::new(0.05, 0.05, 0.05), width: 0.3 }),
+    )));
+    let scene = Arc::new(Scene::new(objects, LightList { lights: Vec::new() }, Background::Sky));
+
+    // terminal cells are about twice as tall as they are wide
+    let (width, height) = (72, 24);
+    let camera = Arc::new(Camera::with_model(
+        CameraFrame::look_at(Point3::new(0.0, 1.5, 6.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        Box::new(Perspective::new(30.0, f64::from(width) / f64::from(height) / 2.0, 0.0, 6.0)),
+    ));
+    let integrator = Arc::new(PathIntegrator { max_depth: 20, rr_min_depth: 3, next_event: false });
+    let image = Renderer::new(integrator, width, height).with_samples(32).render(&scene, &camera);
+
+    let ramp = b" .:-=+*#%@";
+    for row in image.pixels.chunks(width as usize) {
+        let line: String = row
+            .iter()
+            .map(|pixel| {
+                let luminance = (0.2126 * pixel.x + 0.7152 * pixel.y + 0.0722 * pixel.z).clamp(0.0, 1.0).sqrt();
+                ramp[((luminance * (ramp.len() - 1) as f64).round()) as usize] as char
+            })
+            .collect();
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,43 @@
+// Builds a small scene in code, renders it into an image and saves it.
+//
+//     cargo run --release --example spheres -- spheres.ppm
+
+use std::path::Path;
+use std::sync::Arc;
+
+use ray_tracing_in_one_weekend::integrator::path::PathIntegrator;
+use ray_tracing_in_one_weekend::one_week::light::PointLight;
+use ray_tracing_in_one_weekend::one_week::material::{Dielectric, Lambertian, Metal};
+use ray_tracing_in_one_weekend::one_week::sphere::Sphere;
+use ray_tracing_in_one_weekend::{
+    Background, Camera, CameraFrame, Color, HittableList, LightList, Perspective, Point3, Renderer, Scene, Vec3,
+};
+
+fn main () -> std::io::Result<()> {
+    let mut objects = HittableList { objects: Vec::new() };
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) }),
+    )));
+    objects.add(Arc::new(Sphere::new(Point3::new(-2.2, 1.0, 0.0), 1.0, Box::new(Lambertian { albedo: Color::new(0.7, 0.2, 0.1) }))));
+    objects.add(Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, Box::new(Dielectric::new(1.5)))));
+    objects.add(Arc::new(Sphere::new(Point3::new(2.2, 1.0, 0.0), 1.0, Box::new(Metal::new(Color::new(0.8, 0.8, 0.7), 0.1)))));
+
+    let mut lights = LightList { lights: Vec::new() };
+    lights.add(Arc::new(PointLight::new(Point3::new(0.0, 6.0, 4.0), Color::new(40.0, 40.0, 40.0))));
+    let scene = Arc::new(Scene::new(objects, lights, Background::Sky));
+
+    let (width, height) = (400, 225);
+    let camera = Arc::new(Camera::with_model(
+        CameraFrame::look_at(Point3::new(0.0, 2.0, 9.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        Box::new(Perspective::new(30.0, f64::from(width) / f64::from(height), 0.0, 9.0)),
+    ));
+    let integrator = Arc::new(PathIntegrator { max_depth: 50, rr_min_depth: 5, next_event: true });
+
+    let image = Renderer::new(integrator, width, height).with_samples(64).render(&scene, &camera);
+
+    // .png, .pfm or .ppm by the extension
+    let path = std::env::args().nth(1).unwrap_or_else(|| String::from("spheres.ppm"));
+    image.save(Path::new(&path))
+}
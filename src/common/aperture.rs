@@ -0,0 +1,135 @@
+use std::f64::consts::PI;
+use std::io::{Error, ErrorKind};
+
+use crate::common::vec3::Point3;
+use crate::common::vec3_opts::random_in_unit_disk;
+use crate::one_week::random_f64_01;
+use crate::one_week::texture::Texture;
+
+// Cells per side when a mask texture is turned into a distribution.
+const MASK_RESOLUTION: usize = 64;
+
+// Shape of the opening light passes through, in coordinates scaled so that the
+// aperture's radius is one. Out of focus highlights take this shape.
+pub enum Aperture {
+    Circular,
+    // regular polygon of `blades` sides inside the unit circle, turned by `rotation` radians
+    Polygon { blades: usize, rotation: f64 },
+    // grey levels over [-1, 1]², darker parts letting less light through
+    Mask(MaskDistribution),
+}
+
+// Piecewise constant density over the cells of a mask, sampled row then column.
+pub struct MaskDistribution {
+    values: Vec<f64>,
+    // running totals of the rows, then of the cells within each row
+    row_cdf: Vec<f64>,
+    cell_cdf: Vec<f64>,
+}
+
+fn pick (cdf: &[f64], u: f64) -> usize {
+    let total = cdf[cdf.len() - 1];
+    cdf.partition_point(|&c| c <= u * total).min(cdf.len() - 1)
+}
+
+impl Aperture {
+    pub fn polygon (blades: usize, rotation: f64) -> Self {
+        Aperture::Polygon { blades: blades.max(3), rotation }
+    }
+
+    // The mask's first channel over (u, v) in [0, 1]² stretched across the aperture.
+    // A mask that lets no light through has no area to sample and is refused.
+    pub fn mask (texture: &dyn Texture) -> std::io::Result<Self> {
+        let n = MASK_RESOLUTION;
+        let mut values = Vec::with_capacity(n * n);
+        for j in 0..n {
+            for i in 0..n {
+                let (u, v) = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                values.push(texture.value(u, v, &Point3::default()).x.clamp(0.0, 1.0));
+            }
+        }
+        let mut row_cdf = Vec::with_capacity(n);
+        let mut cell_cdf = Vec::with_capacity(n * n);
+        let mut total = 0.0;
+        for row in values.chunks(n) {
+            let mut running = 0.0;
+            for value in row {
+                running += value;
+                cell_cdf.push(running);
+            }
+            total += running;
+            row_cdf.push(total);
+        }
+        if total <= 0.0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "aperture: mask lets no light through"));
+        }
+        Ok(Aperture::Mask(MaskDistribution { values, row_cdf, cell_cdf }))
+    }
+
+    // Fraction of light let through at (x, y).
+    pub fn transmission (&self, x: f64, y: f64) -> f64 {
+        match self {
+            Aperture::Circular => if x * x + y * y <= 1.0 { 1.0 } else { 0.0 },
+            Aperture::Polygon { blades, rotation } => {
+                let sector = 2.0 * PI / *blades as f64;
+                let offset = (y.atan2(x) - rotation).rem_euclid(sector) - sector / 2.0;
+                if (x * x + y * y).sqrt() * offset.cos() <= (sector / 2.0).cos() { 1.0 } else { 0.0 }
+            }
+            Aperture::Mask(mask) => {
+                let n = MASK_RESOLUTION as f64;
+                let (i, j) = (((x + 1.0) / 2.0 * n).floor(), ((y + 1.0) / 2.0 * n).floor());
+                if i < 0.0 || j < 0.0 || i >= n || j >= n {
+                    return 0.0;
+                }
+                mask.values[j as usize * MASK_RESOLUTION + i as usize]
+            }
+        }
+    }
+
+    // Integral of `transmission` over the plane.
+    pub fn area (&self) -> f64 {
+        match self {
+            Aperture::Circular => PI,
+            Aperture::Polygon { blades, .. } => {
+                let n = *blades as f64;
+                n / 2.0 * (2.0 * PI / n).sin()
+            }
+            Aperture::Mask(mask) => {
+                let cell = 2.0 / MASK_RESOLUTION as f64;
+                mask.row_cdf[MASK_RESOLUTION - 1] * cell * cell
+            }
+        }
+    }
+
+    // A point drawn in proportion to `transmission`.
+    pub fn sample (&self) -> (f64, f64) {
+        match self {
+            Aperture::Circular => {
+                let p = random_in_unit_disk();
+                (p.x, p.y)
+            }
+            Aperture::Polygon { blades, rotation } => {
+                // a uniform point in one of the triangles fanning out from the centre
+                let sector = 2.0 * PI / *blades as f64;
+                let k = ((random_f64_01() * *blades as f64) as usize).min(blades - 1) as f64;
+                let (a, b) = (rotation + k * sector, rotation + (k + 1.0) * sector);
+                let (mut r1, mut r2) = (random_f64_01(), random_f64_01());
+                if r1 + r2 > 1.0 {
+                    r1 = 1.0 - r1;
+                    r2 = 1.0 - r2;
+                }
+                (r1 * a.cos() + r2 * b.cos(), r1 * a.sin() + r2 * b.sin())
+            }
+            Aperture::Mask(mask) => {
+                let n = MASK_RESOLUTION;
+                let j = pick(&mask.row_cdf, random_f64_01());
+                let i = pick(&mask.cell_cdf[j * n..(j + 1) * n], random_f64_01());
+                let cell = 2.0 / n as f64;
+                (
+                    -1.0 + (i as f64 + random_f64_01()) * cell,
+                    -1.0 + (j as f64 + random_f64_01()) * cell,
+                )
+            }
+        }
+    }
+}
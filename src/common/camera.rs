@@ -1,59 +1,311 @@
+use crate::common::aperture::Aperture;
 use crate::common::ray::Ray;
 use crate::common::vec3::{Point3, Vec3};
-use crate::common::vec3_opts::{unit_vector, cross, random_in_unit_sphere, random_in_unit_disk};
+use crate::common::vec3_opts::{unit_vector, cross, dot};
 
 use crate::one_week::{degrees_to_radians, random_f64};
 
+// Height of a 35mm film frame, in metres, which gives thin lenses a focal length.
+const FILM_HEIGHT_35MM: f64 = 0.024;
+// Calibration constant of the light meter, from the usual saturation based speed.
+const METER_CALIBRATION: f64 = 120.0;
+
+// Where the camera stands and which way it faces: `u` points right, `v` up and `w`
+// back, away from what the camera looks at.
+#[derive(Clone, Copy)]
+pub struct CameraFrame {
+    pub origin: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl CameraFrame {
+    pub fn look_at (lookfrom: Point3, lookat: Point3, vup: Vec3) -> Self {
+        let w = unit_vector(&(lookfrom - lookat));
+        let u = unit_vector(&cross(&vup, &w));
+        let v = cross(&w, &u);
+        Self { origin: lookfrom, u, v, w }
+    }
+
+    // World direction of `x` right, `y` up and `z` back in the camera's terms.
+    pub fn local (&self, x: f64, y: f64, z: f64) -> Vec3 {
+        self.u * x + self.v * y + self.w * z
+    }
+}
+
+// A point seen from the camera: where it lands on the film and how strongly
+// the camera responds to light arriving from it.
+pub struct CameraSample {
+    pub s: f64,
+    pub t: f64,
+    pub lens_point: Point3,
+    // unit direction from the point towards the lens
+    pub wi: Vec3,
+    pub importance: f64,
+    // solid angle density at the point
+    pub pdf: f64,
+}
+
+// Projection from film positions (s, t), both in [0, 1) with t upwards, to rays
+// leaving a camera placed at `frame`.
+pub trait CameraModel: Send + Sync {
+    // A ray and the weight of the radiance it brings back, one for an ideal camera.
+    // None where the film sees nothing, such as outside a fisheye's image circle.
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)>;
+
+    // Solid angle density of `get_ray` producing the unit `direction` from `lens_point`.
+    // Projections that light paths can't be connected to keep the defaults, and
+    // bidirectional integrators only trace them from the camera side.
+    fn pdf_we (&self, _frame: &CameraFrame, _lens_point: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // Picks a point on the lens and projects `p` through it onto the film.
+    fn sample_wi (&self, _frame: &CameraFrame, _p: &Point3) -> Option<CameraSample> {
+        None
+    }
+}
+
+// Photographic exposure, in scene units of radiance taken as cd/m².
+#[derive(Clone, Copy)]
+pub struct Exposure {
+    pub f_number: f64,
+    // seconds the shutter stays open
+    pub shutter_speed: f64,
+    pub iso: f64,
+}
+
+impl Exposure {
+    // Factor from radiance to film values, so that the brightest radiance the meter
+    // expects at these settings comes out white: sunlit scenes need around f/16 at
+    // 1/100s and ISO 100, like on film.
+    pub fn scale (&self) -> f64 {
+        self.shutter_speed * self.iso / (METER_CALIBRATION * self.f_number * self.f_number)
+    }
+}
+
 pub struct Camera {
-    origin: Point3,
-    lower_left_corner: Point3,
-    horizontal: Vec3,
-    vertical: Vec3,
-    u: Vec3, v: Vec3, w: Vec3,
-    lens_radius: f64,
+    pub frame: CameraFrame,
+    pub model: Box<dyn CameraModel>,
+    // times the shutter opens and closes, rays being spread between them, see `Ray::time`
+    pub shutter: (f64, f64),
+    pub exposure: Option<Exposure>,
 }
 
 impl Camera {
-    pub fn new (
-        lookfrom: Point3,
-        lookat: Point3,
-        vup: Vec3,
-        vfov: f64,
-        aspect_ratio: f64,
-        aperture: f64,
-        focus_dist: f64,
-    ) -> Self {
+    pub fn with_model (frame: CameraFrame, model: Box<dyn CameraModel>) -> Self {
+        Self { frame, model, shutter: (0.0, 0.0), exposure: None }
+    }
+
+    // Meters the film with `exposure`, the shutter staying open from time 0 for its
+    // shutter speed. The model's aperture is left alone, see `with_f_number`.
+    pub fn with_exposure (mut self, exposure: Exposure) -> Self {
+        self.shutter = (0.0, exposure.shutter_speed);
+        self.exposure = Some(exposure);
+        self
+    }
+
+    // Factor applied to the film before it is written out.
+    pub fn exposure_scale (&self) -> f64 {
+        self.exposure.map_or(1.0, |exposure| exposure.scale())
+    }
+
+    pub fn get_ray (&self, s: f64, t: f64) -> Option<(f64, Ray)> {
+        let (weight, mut r) = self.model.get_ray(&self.frame, s, t)?;
+        if self.shutter.1 > self.shutter.0 {
+            r.time = random_f64(self.shutter.0, self.shutter.1);
+        }
+        Some((weight, r))
+    }
+
+    pub fn pdf_we (&self, lens_point: &Point3, direction: &Vec3) -> f64 {
+        self.model.pdf_we(&self.frame, lens_point, direction)
+    }
+
+    pub fn sample_wi (&self, p: &Point3) -> Option<CameraSample> {
+        self.model.sample_wi(&self.frame, p)
+    }
+}
+
+pub struct Perspective {
+    viewport_width: f64,
+    viewport_height: f64,
+    lens_radius: f64,
+    aperture: Aperture,
+    focus_dist: f64,
+    // film offset across and up, in film widths and heights
+    shift: (f64, f64),
+    // normal of the plane in focus, in camera coordinates
+    focus_normal: Vec3,
+}
+
+impl Perspective {
+    pub fn new (vfov: f64, aspect_ratio: f64, aperture: f64, focus_dist: f64) -> Self {
         let theta = degrees_to_radians(vfov);
         let h = (theta / 2.0).tan();
         let viewport_height = 2.0 * h;
-        let viewport_width = aspect_ratio * viewport_height;
+        Self {
+            viewport_width: aspect_ratio * viewport_height,
+            viewport_height,
+            lens_radius: aperture / 2.0,
+            aperture: Aperture::Circular,
+            focus_dist,
+            shift: (0.0, 0.0),
+            focus_normal: Vec3::new(0.0, 0.0, 1.0),
+        }
+    }
 
-        let w = unit_vector(&(lookfrom - lookat));
-        let u = unit_vector(&cross(&vup, &w));
-        let v = cross(&w, &u);
+    // Focal length for a 35mm film with this field of view, in metres.
+    pub fn focal_length (&self) -> f64 {
+        FILM_HEIGHT_35MM / self.viewport_height
+    }
 
-        let origin = lookfrom;
-        let horizontal = u * focus_dist * viewport_width;
-        let vertical = v * focus_dist * viewport_height;
-        let lower_left_corner = origin - horizontal/2.0 - vertical/2.0 - w * focus_dist;
-        let lens_radius = aperture / 2.0;
+    // Opens the lens to the focal length over `f_number`, scene units being metres.
+    pub fn with_f_number (mut self, f_number: f64) -> Self {
+        self.lens_radius = self.focal_length() / f_number / 2.0;
+        self
+    }
 
-        Self {
-            u, v, w,
-            origin,
-            horizontal,
-            vertical,
-            lower_left_corner,
-            lens_radius,
-        }
-    }
-
-    pub fn get_ray (&self, s: f64, t: f64) -> Ray {
-        let rd = random_in_unit_disk() * self.lens_radius;
-        let offset = self.u * rd.x + self.v * rd.y;
-        return Ray::new(
-            &(self.origin + offset),
-            &(self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin)
-        );
+    // Replaces the round opening of the lens, which shapes the bokeh.
+    pub fn with_aperture (mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    // Slides the film sideways by `x` of its width and up by `y` of its height,
+    // moving the view without turning the camera.
+    pub fn with_shift (mut self, x: f64, y: f64) -> Self {
+        self.shift = (x, y);
+        self
+    }
+
+    // Tilts the plane in focus by `tilt` degrees about the horizontal axis and swings
+    // it by `swing` degrees about the vertical one, as a tilted lens would. Positive
+    // angles bring the bottom and the right side of the view closer: the plane is
+    // `x tan(swing) - y tan(tilt) / cos(swing)` nearer than `focus_dist` at x right, y up.
+    pub fn with_tilt (mut self, tilt: f64, swing: f64) -> Self {
+        let (tilt, swing) = (degrees_to_radians(tilt), degrees_to_radians(swing));
+        self.focus_normal = Vec3::new(-swing.sin() * tilt.cos(), tilt.sin(), swing.cos() * tilt.cos());
+        self
+    }
+
+    pub fn viewport_width (&self) -> f64 {
+        self.viewport_width
+    }
+
+    // Lower left corner and edges of the image rectangle on the plane in focus.
+    fn film (&self, frame: &CameraFrame) -> (Point3, Vec3, Vec3) {
+        let horizontal = frame.u * self.focus_dist * self.viewport_width;
+        let vertical = frame.v * self.focus_dist * self.viewport_height;
+        let lower_left_corner = frame.origin - horizontal * (0.5 - self.shift.0) - vertical * (0.5 - self.shift.1)
+            - frame.w * self.focus_dist;
+        (lower_left_corner, horizontal, vertical)
+    }
+
+    // area of the image rectangle one unit in front of the lens
+    fn film_area (&self) -> f64 {
+        self.viewport_width * self.viewport_height
+    }
+
+    fn lens_area (&self) -> f64 {
+        if self.lens_radius > 0.0 {
+            self.aperture.area() * self.lens_radius * self.lens_radius
+        } else {
+            1.0
+        }
+    }
+
+    // A point on the lens drawn in proportion to how much light it lets through, and
+    // that fraction.
+    fn sample_lens (&self, frame: &CameraFrame) -> (Point3, f64) {
+        if self.lens_radius <= 0.0 {
+            return (frame.origin, 1.0);
+        }
+        let (x, y) = self.aperture.sample();
+        let lens_point = frame.origin + frame.local(x * self.lens_radius, y * self.lens_radius, 0.0);
+        (lens_point, self.aperture.transmission(x, y))
+    }
+
+    // Where a ray from `from` along `direction` meets the plane in focus, if ahead.
+    fn focus_point (&self, frame: &CameraFrame, from: &Point3, direction: &Vec3) -> Option<Point3> {
+        let normal = frame.local(self.focus_normal.x, self.focus_normal.y, self.focus_normal.z);
+        let on_plane = frame.origin - frame.w * self.focus_dist;
+        let t = dot(&(on_plane - *from), &normal) / dot(direction, &normal);
+        if !t.is_finite() || t <= 0.0 {
+            return None;
+        }
+        Some(*from + *direction * t)
+    }
+
+    // Film coordinates of the camera ray leaving `lens_point` along the unit `direction`,
+    // and the solid angle density of `get_ray` making it. The lens centre projects the
+    // point in focus back onto the film.
+    fn film_position (&self, frame: &CameraFrame, lens_point: &Point3, direction: &Vec3) -> Option<(f64, f64, f64)> {
+        let cos_lens = -dot(direction, &frame.w);
+        if cos_lens <= 0.0 {
+            return None;
+        }
+        let focus = self.focus_point(frame, lens_point, direction)?;
+        let central = focus - frame.origin;
+        let cos_centre = -dot(&central, &frame.w) / central.length();
+        if cos_centre <= 0.0 {
+            return None;
+        }
+
+        let (lower_left_corner, horizontal, vertical) = self.film(frame);
+        let on_film = frame.origin + central * (self.focus_dist / (cos_centre * central.length())) - lower_left_corner;
+        let s = dot(&on_film, &frame.u) / horizontal.length();
+        let t = dot(&on_film, &frame.v) / vertical.length();
+        if !(0.0..1.0).contains(&s) || !(0.0..1.0).contains(&t) {
+            return None;
+        }
+
+        // uniform on the film one unit out, taken to the plane in focus through the
+        // lens centre, then seen from the lens point
+        let normal = frame.local(self.focus_normal.x, self.focus_normal.y, self.focus_normal.z);
+        let to_focus = focus - *lens_point;
+        let film_per_focus = dot(&central, &normal).abs() / (central.length_squared() * central.length() * cos_centre.powi(3));
+        let focus_per_solid_angle = to_focus.length_squared() * to_focus.length() / dot(&to_focus, &normal).abs();
+        Some((s, t, film_per_focus * focus_per_solid_angle / self.film_area()))
+    }
+}
+
+impl CameraModel for Perspective {
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)> {
+        let (lower_left_corner, horizontal, vertical) = self.film(frame);
+        let on_film = lower_left_corner + horizontal * s + vertical * t;
+        let focus = self.focus_point(frame, &frame.origin, &(on_film - frame.origin))?;
+        let (lens_point, _) = self.sample_lens(frame);
+        Some((1.0, Ray::new(&lens_point, &(focus - lens_point))))
+    }
+
+    fn pdf_we (&self, frame: &CameraFrame, lens_point: &Point3, direction: &Vec3) -> f64 {
+        self.film_position(frame, lens_point, direction).map_or(0.0, |(_, _, pdf)| pdf)
+    }
+
+    // Importance is the direction density over the lens area and the cosine at the
+    // lens. The lens' transmission at the chosen point scales the importance and the
+    // density alike, so the estimate doesn't depend on it.
+    fn sample_wi (&self, frame: &CameraFrame, p: &Point3) -> Option<CameraSample> {
+        let (lens_point, transmission) = self.sample_lens(frame);
+        if transmission <= 0.0 {
+            return None;
+        }
+
+        let to_lens = lens_point - *p;
+        let distance = to_lens.length();
+        let wi = to_lens / distance;
+        let (s, t, pdf_we) = self.film_position(frame, &lens_point, &-wi)?;
+
+        let cos_theta = dot(&wi, &frame.w);
+        Some(CameraSample {
+            s,
+            t,
+            lens_point,
+            wi,
+            importance: transmission * pdf_we / (self.lens_area() * cos_theta),
+            pdf: transmission * distance * distance / (cos_theta * self.lens_area()),
+        })
     }
 }
@@ -1,20 +1,14 @@
 use crate::common::vec3::Color;
 use crate::one_week::clamp;
 
-pub fn format_color(pixel_color: &Color, samples_per_pixel: i32) -> String {
-    let mut r = pixel_color.x;
-    let mut g = pixel_color.y;
-    let mut b = pixel_color.z;
-
+// 8-bit gamma 2 values of the mean of `samples_per_pixel` summed samples.
+pub fn color_bytes(pixel_color: &Color, samples_per_pixel: i32) -> [u8; 3] {
     let scale = 1.0 / f64::from(samples_per_pixel);
-    r = (scale * r).sqrt();
-    g = (scale * g).sqrt();
-    b = (scale * b).sqrt();
+    let byte = |c: f64| (255.999 * clamp((scale * c).sqrt(), 0.0, 1.0 - 1e-8)) as u8;
+    [byte(pixel_color.x), byte(pixel_color.y), byte(pixel_color.z)]
+}
 
-    return format!(
-        "{} {} {}",
-        (255.999 * clamp(r, 0.0, 1.0 - 1e-8)) as i32,
-        (255.999 * clamp(g, 0.0, 1.0 - 1e-8)) as i32,
-        (255.999 * clamp(b, 0.0, 1.0 - 1e-8)) as i32,
-    )
-}
\ No newline at end of file
+pub fn format_color(pixel_color: &Color, samples_per_pixel: i32) -> String {
+    let [r, g, b] = color_bytes(pixel_color, samples_per_pixel);
+    format!("{} {} {}", r, g, b)
+}
@@ -0,0 +1,162 @@
+use crate::common::image::Image;
+use crate::common::vec3::Color;
+use crate::common::vec3_opts::dot;
+
+// B3 spline taps of the à-trous wavelet, spread 2^i pixels apart in pass i.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+fn luminance (c: &Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+// Edge-avoiding à-trous wavelet filter guided by albedo and normal AOVs, after
+// Dammertz et al. and SVGF. Lighting is divided by the albedo first so texture
+// survives, and smoothed where neighbours face the same way, share an albedo and
+// differ in brightness by no more than the noise explains. The noise is estimated
+// from each pixel's neighbourhood and filtered along with the image.
+pub struct Denoiser {
+    pub iterations: usize,
+    // brightness differences tolerated, in standard deviations of the noise
+    pub sigma_luminance: f64,
+    // exponent on the cosine between normals
+    pub sigma_normal: f64,
+    pub sigma_albedo: f64,
+}
+
+impl Default for Denoiser {
+    fn default () -> Self {
+        Self { iterations: 5, sigma_luminance: 4.0, sigma_normal: 16.0, sigma_albedo: 0.3 }
+    }
+}
+
+impl Denoiser {
+    // `color` is linear and not yet tone mapped. `albedo` and `normal` are per pixel
+    // means, normals in world space and zero where nothing was hit. Panics if the
+    // sizes differ.
+    pub fn denoise (&self, color: &Image, albedo: &Image, normal: &Image) -> Image {
+        let (width, height) = (color.width, color.height);
+        assert!(
+            albedo.width == width && albedo.height == height && normal.width == width && normal.height == height,
+            "guides of a different size"
+        );
+
+        let demodulate = |c: f64, a: f64| if a > 1e-3 { c / a } else { c };
+        let remodulate = |c: f64, a: f64| if a > 1e-3 { c * a } else { c };
+        let mut lighting = Image::from_pixels(
+            width,
+            height,
+            color
+                .pixels
+                .iter()
+                .zip(&albedo.pixels)
+                .map(|(c, a)| Color::new(demodulate(c.x, a.x), demodulate(c.y, a.y), demodulate(c.z, a.z)))
+                .collect(),
+        );
+        let normals: Vec<Color> = normal
+            .pixels
+            .iter()
+            .map(|n| if n.length_squared() > 1e-8 { *n / n.length() } else { Color::default() })
+            .collect();
+        let mut variance = local_variance(&lighting);
+
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let blurred = blur_3x3(&variance, width, height);
+            let mut next = Image::new(width, height);
+            let mut next_variance = vec![0.0; width * height];
+            for y in 0..height {
+                for x in 0..width {
+                    let p = y * width + x;
+                    let (cp, np, ap) = (lighting.pixels[p], normals[p], albedo.pixels[p]);
+                    let lp = luminance(&cp);
+                    let scale = self.sigma_luminance * blurred[p].sqrt() + 1e-6;
+
+                    let (mut sum, mut sum_variance, mut total) = (Color::default(), 0.0, 0.0);
+                    for (j, ky) in KERNEL.iter().enumerate() {
+                        let qy = y as i64 + (j as i64 - 2) * step;
+                        if qy < 0 || qy >= height as i64 {
+                            continue;
+                        }
+                        for (i, kx) in KERNEL.iter().enumerate() {
+                            let qx = x as i64 + (i as i64 - 2) * step;
+                            if qx < 0 || qx >= width as i64 {
+                                continue;
+                            }
+                            let q = qy as usize * width + qx as usize;
+                            let (cq, nq, aq) = (lighting.pixels[q], normals[q], albedo.pixels[q]);
+
+                            let w_luminance = (-(lp - luminance(&cq)).abs() / scale).exp();
+                            let w_normal = if np.near_zero() && nq.near_zero() {
+                                1.0
+                            } else {
+                                dot(&np, &nq).max(0.0).powf(self.sigma_normal)
+                            };
+                            let w_albedo = (-(ap - aq).length_squared() / (self.sigma_albedo * self.sigma_albedo)).exp();
+                            let w = kx * ky * w_luminance * w_normal * w_albedo;
+
+                            sum += cq * w;
+                            sum_variance += w * w * variance[q];
+                            total += w;
+                        }
+                    }
+                    // the centre tap always has full weight, so `total` is never zero
+                    next.pixels[p] = sum / total;
+                    next_variance[p] = sum_variance / (total * total);
+                }
+            }
+            lighting = next;
+            variance = next_variance;
+        }
+
+        let pixels = lighting
+            .pixels
+            .iter()
+            .zip(&albedo.pixels)
+            .map(|(c, a)| Color::new(remodulate(c.x, a.x), remodulate(c.y, a.y), remodulate(c.z, a.z)))
+            .collect();
+        Image::from_pixels(width, height, pixels)
+    }
+}
+
+// Luminance variance over each pixel's 5×5 neighbourhood.
+fn local_variance (image: &Image) -> Vec<f64> {
+    let (width, height) = (image.width, image.height);
+    let mut variance = vec![0.0; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let (mut sum, mut sum_squares, mut count) = (0.0, 0.0, 0.0);
+            for qy in y.saturating_sub(2)..(y + 3).min(height) {
+                for qx in x.saturating_sub(2)..(x + 3).min(width) {
+                    let l = luminance(&image.get(qx, qy));
+                    sum += l;
+                    sum_squares += l * l;
+                    count += 1.0;
+                }
+            }
+            let mean = sum / count;
+            variance[y * width + x] = (sum_squares / count - mean * mean).max(0.0);
+        }
+    }
+    variance
+}
+
+fn blur_3x3 (values: &[f64], width: usize, height: usize) -> Vec<f64> {
+    let taps = [0.25, 0.5, 0.25];
+    let mut blurred = vec![0.0; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let (mut sum, mut total) = (0.0, 0.0);
+            for (j, ky) in taps.iter().enumerate() {
+                for (i, kx) in taps.iter().enumerate() {
+                    let (qx, qy) = (x as i64 + i as i64 - 1, y as i64 + j as i64 - 1);
+                    if qx >= 0 && qy >= 0 && (qx as usize) < width && (qy as usize) < height {
+                        sum += kx * ky * values[qy as usize * width + qx as usize];
+                        total += kx * ky;
+                    }
+                }
+            }
+            blurred[y * width + x] = sum / total;
+        }
+    }
+    blurred
+}
@@ -0,0 +1,72 @@
+use crate::common::image::Image;
+
+fn attribute (file: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    file.extend_from_slice(name.as_bytes());
+    file.push(0);
+    file.extend_from_slice(kind.as_bytes());
+    file.push(0);
+    file.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    file.extend_from_slice(value);
+}
+
+// Scanline OpenEXR of 32-bit float RGB layers, uncompressed. Each layer's channels are
+// named `layer.R`, `layer.G` and `layer.B`, plain `R`, `G` and `B` for an empty name.
+// Panics unless the layers are all the same size.
+pub fn encode_exr (layers: &[(&str, &Image)]) -> Vec<u8> {
+    let (width, height) = (layers[0].1.width, layers[0].1.height);
+    assert!(layers.iter().all(|(_, image)| image.width == width && image.height == height), "layers of different sizes");
+
+    // channels go in name order, which also orders their data within a scanline
+    let mut channels: Vec<(String, usize, usize)> = Vec::new();
+    for (index, (name, _)) in layers.iter().enumerate() {
+        for (component, letter) in ["R", "G", "B"].iter().enumerate() {
+            let full = if name.is_empty() { letter.to_string() } else { format!("{}.{}", name, letter) };
+            channels.push((full, index, component));
+        }
+    }
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // magic, then version 2 with long names allowed
+    let mut file = vec![0x76, 0x2f, 0x31, 0x01, 2, 4, 0, 0];
+    let mut list = Vec::new();
+    for (name, _, _) in &channels {
+        list.extend_from_slice(name.as_bytes());
+        list.push(0);
+        // float, not perceptually linear, reserved, x and y sampling
+        list.extend_from_slice(&2i32.to_le_bytes());
+        list.extend_from_slice(&[0, 0, 0, 0]);
+        list.extend_from_slice(&1i32.to_le_bytes());
+        list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    list.push(0);
+    attribute(&mut file, "channels", "chlist", &list);
+    attribute(&mut file, "compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+    attribute(&mut file, "dataWindow", "box2i", &window);
+    attribute(&mut file, "displayWindow", "box2i", &window);
+    attribute(&mut file, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut file, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    attribute(&mut file, "screenWindowCenter", "v2f", &[0.0f32.to_le_bytes(), 0.0f32.to_le_bytes()].concat());
+    attribute(&mut file, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    file.push(0);
+
+    // one scanline per block, found through a table of where each starts
+    let line_size = channels.len() * width * 4;
+    let table_end = file.len() + height * 8;
+    for y in 0..height {
+        file.extend_from_slice(&((table_end + y * (line_size + 8)) as u64).to_le_bytes());
+    }
+    for y in 0..height {
+        file.extend_from_slice(&(y as i32).to_le_bytes());
+        file.extend_from_slice(&(line_size as i32).to_le_bytes());
+        for &(_, index, component) in &channels {
+            let image = layers[index].1;
+            for x in 0..width {
+                let pixel = image.get(x, y);
+                let value = [pixel.x, pixel.y, pixel.z][component] as f32;
+                file.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+    file
+}
@@ -0,0 +1,93 @@
+use crate::common::image::Image;
+use crate::common::stats::RayStats;
+use crate::common::vec3::Color;
+
+// What a render accumulates before it becomes an image: summed camera samples with
+// how many went into each pixel, light tracing splats, and any named auxiliary
+// channels, which are summed per camera sample like the colour. Also what the render
+// cost, in rays and wall clock seconds.
+#[derive(Clone, Debug)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub color: Image,
+    pub samples: Vec<u32>,
+    // spread over every camera sample of the image rather than one pixel's
+    pub splats: Image,
+    pub channels: Vec<(String, Image)>,
+    pub stats: RayStats,
+    pub seconds: f64,
+}
+
+impl Framebuffer {
+    pub fn new (width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            color: Image::new(width, height),
+            samples: vec![0; width * height],
+            splats: Image::new(width, height),
+            channels: Vec::new(),
+            stats: RayStats::default(),
+            seconds: 0.0,
+        }
+    }
+
+    pub fn with_channel (mut self, name: &str) -> Self {
+        if self.channel(name).is_none() {
+            self.channels.push((name.to_string(), Image::new(self.width, self.height)));
+        }
+        self
+    }
+
+    // The summed values of a channel.
+    pub fn channel (&self, name: &str) -> Option<&Image> {
+        self.channels.iter().find(|(channel, _)| channel == name).map(|(_, image)| image)
+    }
+
+    pub fn channel_mut (&mut self, name: &str) -> Option<&mut Image> {
+        self.channels.iter_mut().find(|(channel, _)| channel == name).map(|(_, image)| image)
+    }
+
+    // `count` camera samples adding up to `sum` for pixel (x, y), from the top left.
+    pub fn add_samples (&mut self, x: usize, y: usize, sum: Color, count: u32) {
+        let i = y * self.width + x;
+        self.color.pixels[i] += sum;
+        self.samples[i] += count;
+    }
+
+    pub fn add_splat (&mut self, x: usize, y: usize, color: Color) {
+        self.splats.pixels[y * self.width + x] += color;
+    }
+
+    pub fn total_samples (&self) -> u64 {
+        self.samples.iter().map(|&count| u64::from(count)).sum()
+    }
+
+    // Mean of the camera samples in every pixel plus the splats, each of which stands
+    // for one pixel's worth of the whole image's samples.
+    pub fn resolve (&self) -> Image {
+        let per_pixel = self.total_samples() as f64 / (self.width * self.height).max(1) as f64;
+        let splat_scale = if per_pixel > 0.0 { 1.0 / per_pixel } else { 0.0 };
+        let mut image = self.mean(&self.color);
+        for (pixel, splat) in image.pixels.iter_mut().zip(&self.splats.pixels) {
+            *pixel += *splat * splat_scale;
+        }
+        image
+    }
+
+    // Per sample mean of a channel.
+    pub fn resolve_channel (&self, name: &str) -> Option<Image> {
+        self.channel(name).map(|image| self.mean(image))
+    }
+
+    fn mean (&self, sums: &Image) -> Image {
+        let pixels = sums
+            .pixels
+            .iter()
+            .zip(&self.samples)
+            .map(|(&sum, &count)| if count > 0 { sum * (1.0 / f64::from(count)) } else { Color::default() })
+            .collect();
+        Image::from_pixels(self.width, self.height, pixels)
+    }
+}
@@ -0,0 +1,317 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::common::color::{color_bytes, format_color};
+use crate::common::exr::encode_exr;
+use crate::common::png::{decode_png, encode_png, is_png};
+use crate::common::vec3::Color;
+
+fn invalid (format: &str, msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("{}: {}", format, msg))
+}
+
+// File formats an image can be written in. PPM and PNG hold 8-bit gamma 2 values,
+// PFM and EXR the linear floats as they are.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+    Pfm,
+    Exr,
+}
+
+impl ImageFormat {
+    // By file extension, PPM for anything unknown.
+    pub fn from_path (path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("png") => ImageFormat::Png,
+            Some("pfm") => ImageFormat::Pfm,
+            Some("exr") => ImageFormat::Exr,
+            _ => ImageFormat::Ppm,
+        }
+    }
+}
+
+// Linear RGB, `width` by `height`, row by row from the top.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    // All black.
+    pub fn new (width: usize, height: usize) -> Self {
+        Self { width, height, pixels: vec![Color::default(); width * height] }
+    }
+
+    pub fn from_pixels (width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel count doesn't match the size");
+        Self { width, height, pixels }
+    }
+
+    pub fn get (&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set (&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    // The `width` by `height` block with its top left corner at (x, y). Panics unless
+    // it lies within the image.
+    pub fn crop (&self, x: usize, y: usize, width: usize, height: usize) -> Image {
+        assert!(x + width <= self.width && y + height <= self.height, "crop outside the image");
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in y..y + height {
+            pixels.extend_from_slice(&self.pixels[row * self.width + x..row * self.width + x + width]);
+        }
+        Image::from_pixels(width, height, pixels)
+    }
+
+    // Copies `other` in with its top left corner at (x, y), dropping what falls outside.
+    pub fn paste (&mut self, x: usize, y: usize, other: &Image) {
+        for row in 0..other.height.min(self.height.saturating_sub(y)) {
+            for column in 0..other.width.min(self.width.saturating_sub(x)) {
+                self.set(x + column, y + row, other.get(column, row));
+            }
+        }
+    }
+
+    // Box filtered: every new pixel is the area weighted mean of the old pixels it
+    // covers, so shrinking doesn't alias and growing repeats pixels.
+    pub fn resize (&self, width: usize, height: usize) -> Image {
+        let (sx, sy) = (self.width as f64 / width as f64, self.height as f64 / height as f64);
+        // old pixels under [start, end) along an axis and how much of each is covered
+        let spans = |count: usize, scale: f64, limit: usize| -> Vec<Vec<(usize, f64)>> {
+            (0..count)
+                .map(|i| {
+                    let (start, end) = (i as f64 * scale, (i + 1) as f64 * scale);
+                    (start.floor() as usize..(end.ceil() as usize).min(limit))
+                        .map(|k| (k, ((k + 1) as f64).min(end) - (k as f64).max(start)))
+                        .collect()
+                })
+                .collect()
+        };
+        let (columns, rows) = (spans(width, sx, self.width), spans(height, sy, self.height));
+        let mut image = Image::new(width, height);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, column) in columns.iter().enumerate() {
+                let mut sum = Color::default();
+                for &(j, wy) in row {
+                    for &(i, wx) in column {
+                        sum += self.get(i, j) * (wx * wy);
+                    }
+                }
+                image.set(x, y, sum * (1.0 / (sx * sy)));
+            }
+        }
+        image
+    }
+
+    // Bilinear between pixel centres at continuous pixel coordinates, (0, 0) being
+    // the top left corner of the image, clamped at the edges.
+    pub fn sample (&self, x: f64, y: f64) -> Color {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (i, j) = (x.floor(), y.floor());
+        let (fx, fy) = (x - i, y - j);
+        let texel = |i: f64, j: f64| {
+            self.get(i.clamp(0.0, (self.width - 1) as f64) as usize, j.clamp(0.0, (self.height - 1) as f64) as usize)
+        };
+        texel(i, j) * ((1.0 - fx) * (1.0 - fy))
+            + texel(i + 1.0, j) * (fx * (1.0 - fy))
+            + texel(i, j + 1.0) * ((1.0 - fx) * fy)
+            + texel(i + 1.0, j + 1.0) * (fx * fy)
+    }
+
+    // Panics if the sizes differ.
+    pub fn add (&mut self, other: &Image) {
+        assert!(self.width == other.width && self.height == other.height, "adding images of different sizes");
+        for (pixel, other) in self.pixels.iter_mut().zip(&other.pixels) {
+            *pixel += *other;
+        }
+    }
+
+    pub fn scale (&mut self, factor: f64) {
+        for pixel in &mut self.pixels {
+            *pixel *= factor;
+        }
+    }
+
+    pub fn encode (&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Ppm => {
+                let mut text = format!("P3\n{} {}\n255\n", self.width, self.height);
+                for pixel in &self.pixels {
+                    text.push_str(&format_color(pixel, 1));
+                    text.push('\n');
+                }
+                text.into_bytes()
+            }
+            ImageFormat::Png => {
+                let rgb: Vec<u8> = self.pixels.iter().flat_map(|pixel| color_bytes(pixel, 1)).collect();
+                encode_png(self.width, self.height, &rgb)
+            }
+            // little endian, rows from the bottom
+            ImageFormat::Pfm => {
+                let mut bytes = format!("PF\n{} {}\n-1.0\n", self.width, self.height).into_bytes();
+                for row in self.pixels.chunks(self.width).rev() {
+                    for pixel in row {
+                        for channel in [pixel.x, pixel.y, pixel.z] {
+                            bytes.extend_from_slice(&(channel as f32).to_le_bytes());
+                        }
+                    }
+                }
+                bytes
+            }
+            ImageFormat::Exr => encode_exr(&[("", self)]),
+        }
+    }
+
+    // PNG, PFM, or plain (P3) or binary (P6) PPM of eight or sixteen bits, told apart
+    // by their first bytes. 8 and 16-bit values come back as stored, in [0, 1] and
+    // without undoing any gamma, which is what textures and masks want.
+    pub fn decode (bytes: &[u8]) -> std::io::Result<Image> {
+        if is_png(bytes) {
+            let (width, height, pixels) = decode_png(bytes)?;
+            let pixels = pixels.iter().map(|rgb| Color::new(rgb[0], rgb[1], rgb[2])).collect();
+            return Ok(Image::from_pixels(width, height, pixels));
+        }
+        let (header, at) = header(bytes)?;
+        if header[0] == "PF" {
+            return decode_pfm(bytes, &header, at);
+        }
+        decode_ppm(bytes, &header, at)
+    }
+
+    pub fn save (&self, path: &Path) -> std::io::Result<()> {
+        fs::write(path, self.encode(ImageFormat::from_path(path)))
+    }
+
+    pub fn load (path: &Path) -> std::io::Result<Image> {
+        Image::decode(&fs::read(path)?)
+    }
+
+    // `load` for images as `save` wrote them: the gamma 2 of 8 and 16-bit formats is
+    // undone, so a render comes back linear, if clipped to [0, 1].
+    pub fn load_linear (path: &Path) -> std::io::Result<Image> {
+        let bytes = fs::read(path)?;
+        let mut image = Image::decode(&bytes)?;
+        if !bytes.starts_with(b"PF") {
+            for pixel in &mut image.pixels {
+                *pixel = *pixel * *pixel;
+            }
+        }
+        Ok(image)
+    }
+}
+
+// The magic and three numbers every PPM and PFM starts with, and where the data
+// after them begins, one whitespace byte past the last.
+fn header (bytes: &[u8]) -> std::io::Result<(Vec<String>, usize)> {
+    let mut at = 0;
+    let mut header = Vec::new();
+    while header.len() < 4 {
+        while at < bytes.len() && (bytes[at].is_ascii_whitespace() || bytes[at] == b'#') {
+            if bytes[at] == b'#' {
+                while at < bytes.len() && bytes[at] != b'\n' {
+                    at += 1;
+                }
+            } else {
+                at += 1;
+            }
+        }
+        let start = at;
+        while at < bytes.len() && !bytes[at].is_ascii_whitespace() {
+            at += 1;
+        }
+        if start == at {
+            return Err(invalid("ppm", "truncated header"));
+        }
+        header.push(String::from_utf8_lossy(&bytes[start..at]).into_owned());
+    }
+    Ok((header, (at + 1).min(bytes.len())))
+}
+
+fn size (format: &str, header: &[String]) -> std::io::Result<(usize, usize)> {
+    let number = |token: &str| token.parse::<usize>().map_err(|_| invalid(format, "bad header"));
+    let (width, height) = (number(&header[1])?, number(&header[2])?);
+    if width == 0 || height == 0 {
+        return Err(invalid(format, "bad header"));
+    }
+    Ok((width, height))
+}
+
+// Values in a `width` by `height` image of `per_pixel` each, if that can be counted.
+fn value_count (format: &str, width: usize, height: usize, per_pixel: usize) -> std::io::Result<usize> {
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(per_pixel))
+        .ok_or_else(|| invalid(format, "image too large"))
+}
+
+fn decode_ppm (bytes: &[u8], header: &[String], at: usize) -> std::io::Result<Image> {
+    let (width, height) = size("ppm", header)?;
+    let number = |token: &str| token.parse::<usize>().map_err(|_| invalid("ppm", "bad header"));
+    let max = number(&header[3])?;
+    if max == 0 || max > 65535 {
+        return Err(invalid("ppm", "bad header"));
+    }
+
+    let count = value_count("ppm", width, height, 3)?;
+    let samples: Vec<usize> = match header[0].as_str() {
+        "P3" => String::from_utf8_lossy(&bytes[at..])
+            .split_ascii_whitespace()
+            .take(count)
+            .map(number)
+            .collect::<std::io::Result<Vec<usize>>>()?,
+        "P6" => {
+            let data = &bytes[at..];
+            let size = if max < 256 { 1 } else { 2 };
+            if data.len() / size < count {
+                return Err(invalid("ppm", "unexpected end of file"));
+            }
+            (0..count)
+                .map(|i| match size {
+                    1 => usize::from(data[i]),
+                    _ => usize::from(data[2 * i]) << 8 | usize::from(data[2 * i + 1]),
+                })
+                .collect()
+        }
+        _ => return Err(invalid("ppm", "not a PPM file")),
+    };
+    if samples.len() < count {
+        return Err(invalid("ppm", "unexpected end of file"));
+    }
+
+    let scale = 1.0 / max as f64;
+    let pixels = samples
+        .chunks(3)
+        .map(|rgb| Color::new(rgb[0] as f64 * scale, rgb[1] as f64 * scale, rgb[2] as f64 * scale))
+        .collect();
+    Ok(Image::from_pixels(width, height, pixels))
+}
+
+// Colour PFM, the sign of the scale giving the byte order.
+fn decode_pfm (bytes: &[u8], header: &[String], at: usize) -> std::io::Result<Image> {
+    let (width, height) = size("pfm", header)?;
+    let scale = header[3].parse::<f64>().map_err(|_| invalid("pfm", "bad header"))?;
+    let data = &bytes[at..];
+    if data.len() < value_count("pfm", width, height, 12)? {
+        return Err(invalid("pfm", "unexpected end of file"));
+    }
+    let float = |i: usize| {
+        let raw = [data[4 * i], data[4 * i + 1], data[4 * i + 2], data[4 * i + 3]];
+        f64::from(if scale < 0.0 { f32::from_le_bytes(raw) } else { f32::from_be_bytes(raw) })
+    };
+    let mut image = Image::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let i = 3 * (y * width + x);
+            image.set(x, height - 1 - y, Color::new(float(i), float(i + 1), float(i + 2)));
+        }
+    }
+    Ok(image)
+}
@@ -0,0 +1,125 @@
+use std::ops::{Add, Mul, Sub};
+
+use crate::common::camera::CameraFrame;
+use crate::common::vec3::{Point3, Vec3};
+
+// How a track gets from one key to the next.
+#[derive(Clone, Copy)]
+pub enum Interpolation {
+    Linear,
+    // cubic through each key's handles, which flat handles turn into easing in and out
+    Bezier,
+    // smooth curve through every key, tangents taken from the neighbouring keys
+    CatmullRom,
+}
+
+// A value at `time` in seconds, with Bezier handles before and after it.
+#[derive(Clone, Copy)]
+pub struct Key<T> {
+    pub time: f64,
+    pub value: T,
+    pub handles: (T, T),
+}
+
+impl<T: Copy> Key<T> {
+    // Key with flat handles.
+    pub fn new (time: f64, value: T) -> Self {
+        Self { time, value, handles: (value, value) }
+    }
+
+    pub fn with_handles (time: f64, value: T, before: T, after: T) -> Self {
+        Self { time, value, handles: (before, after) }
+    }
+}
+
+// Keyframed value over time, held at the first and last keys outside them.
+#[derive(Clone)]
+pub struct Track<T> {
+    keys: Vec<Key<T>>,
+    interpolation: Interpolation,
+}
+
+impl<T> Track<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T>,
+{
+    // Panics without keys.
+    pub fn new (interpolation: Interpolation, mut keys: Vec<Key<T>>) -> Self {
+        assert!(!keys.is_empty(), "track without keys");
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keys, interpolation }
+    }
+
+    pub fn constant (value: T) -> Self {
+        Self::new(Interpolation::Linear, vec![Key::new(0.0, value)])
+    }
+
+    pub fn is_constant (&self) -> bool {
+        self.keys.len() < 2
+    }
+
+    pub fn times (&self) -> impl Iterator<Item = f64> + '_ {
+        self.keys.iter().map(|key| key.time)
+    }
+
+    pub fn at (&self, time: f64) -> T {
+        let keys = &self.keys;
+        let last = keys.len() - 1;
+        if time <= keys[0].time {
+            return keys[0].value;
+        }
+        if time >= keys[last].time {
+            return keys[last].value;
+        }
+        let i = keys.partition_point(|key| key.time <= time) - 1;
+        let (k0, k1) = (&keys[i], &keys[i + 1]);
+        let span = k1.time - k0.time;
+        let s = (time - k0.time) / span;
+
+        match self.interpolation {
+            Interpolation::Linear => k0.value + (k1.value - k0.value) * s,
+            Interpolation::Bezier => {
+                let (p0, p1, p2, p3) = (k0.value, k0.handles.1, k1.handles.0, k1.value);
+                let r = 1.0 - s;
+                p0 * (r * r * r) + p1 * (3.0 * r * r * s) + p2 * (3.0 * r * s * s) + p3 * (s * s * s)
+            }
+            Interpolation::CatmullRom => {
+                // tangents per second from the keys either side, one sided at the ends
+                let tangent = |j: usize| {
+                    let (a, b) = (&keys[j.saturating_sub(1)], &keys[(j + 1).min(last)]);
+                    (b.value - a.value) * (1.0 / (b.time - a.time))
+                };
+                let (m0, m1) = (tangent(i) * span, tangent(i + 1) * span);
+                let (s2, s3) = (s * s, s * s * s);
+                k0.value * (2.0 * s3 - 3.0 * s2 + 1.0)
+                    + m0 * (s3 - 2.0 * s2 + s)
+                    + k1.value * (3.0 * s2 - 2.0 * s3)
+                    + m1 * (s3 - s2)
+            }
+        }
+    }
+}
+
+// Keyframed placement and lens of a perspective camera.
+pub struct CameraPath {
+    pub lookfrom: Track<Point3>,
+    pub lookat: Track<Point3>,
+    pub vfov: Track<f64>,
+    pub focus_dist: Track<f64>,
+}
+
+impl CameraPath {
+    // A camera that stays where it is.
+    pub fn fixed (lookfrom: Point3, lookat: Point3, vfov: f64, focus_dist: f64) -> Self {
+        Self {
+            lookfrom: Track::constant(lookfrom),
+            lookat: Track::constant(lookat),
+            vfov: Track::constant(vfov),
+            focus_dist: Track::constant(focus_dist),
+        }
+    }
+
+    pub fn frame (&self, time: f64, vup: Vec3) -> CameraFrame {
+        CameraFrame::look_at(self.lookfrom.at(time), self.lookat.at(time), vup)
+    }
+}
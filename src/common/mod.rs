@@ -1,5 +1,19 @@
 pub mod vec3;
 pub mod vec3_opts;
-pub mod color;
+pub(crate) mod color;
 pub mod ray;
-pub mod camera;
\ No newline at end of file
+pub mod camera;
+pub mod aperture;
+pub mod realistic;
+pub mod projection;
+pub mod stereo;
+pub(crate) mod onb;
+pub(crate) mod sampler;
+pub mod spectrum;
+pub mod keyframe;
+pub(crate) mod png;
+pub(crate) mod exr;
+pub mod image;
+pub mod framebuffer;
+pub mod denoise;
+pub(crate) mod stats;
@@ -0,0 +1,27 @@
+use crate::common::vec3::Vec3;
+use crate::common::vec3_opts::{cross, unit_vector};
+
+// Orthonormal basis with `w` along a chosen axis.
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn build_from_w (n: &Vec3) -> Self {
+        let w = unit_vector(n);
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = unit_vector(&cross(&w, &a));
+        let u = cross(&w, &v);
+        Self { u, v, w }
+    }
+
+    pub fn local (&self, a: f64, b: f64, c: f64) -> Vec3 {
+        self.u * a + self.v * b + self.w * c
+    }
+}
@@ -0,0 +1,340 @@
+use std::io::{Error, ErrorKind};
+
+// Largest block deflate can store without compressing.
+const STORED_BLOCK: usize = 65535;
+
+fn crc32 (bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32 (bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn invalid (msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("png: {}", msg))
+}
+
+pub fn is_png (bytes: &[u8]) -> bool {
+    bytes.starts_with(SIGNATURE)
+}
+
+fn write_chunk (file: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    file.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = file.len();
+    file.extend_from_slice(kind);
+    file.extend_from_slice(data);
+    let crc = crc32(&file[start..]);
+    file.extend_from_slice(&crc.to_be_bytes());
+}
+
+// 8-bit RGB rows, top first, as a PNG. The image data is stored rather than
+// compressed, which keeps this free of dependencies.
+pub fn encode_png (width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    let mut file = SIGNATURE.to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, truecolour, default compression, filtering and no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(&mut file, b"IHDR", &header);
+
+    // every row starts with its filter type, none
+    let mut raw = Vec::with_capacity(height * (width * 3 + 1));
+    for row in rgb.chunks(width * 3) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let blocks = raw.chunks(STORED_BLOCK).collect::<Vec<_>>();
+    for (i, block) in blocks.iter().enumerate() {
+        zlib.push(if i + 1 == blocks.len() { 1 } else { 0 });
+        let length = block.len() as u16;
+        zlib.extend_from_slice(&length.to_le_bytes());
+        zlib.extend_from_slice(&(!length).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+    write_chunk(&mut file, b"IDAT", &zlib);
+
+    write_chunk(&mut file, b"IEND", &[]);
+    file
+}
+
+// Bits of a deflate stream, least significant first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits (&mut self, count: usize) -> std::io::Result<usize> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self.data.get(self.bit / 8).ok_or_else(|| invalid("unexpected end of data"))?;
+            value |= usize::from(byte >> (self.bit % 8) & 1) << i;
+            self.bit += 1;
+        }
+        Ok(value)
+    }
+
+    fn align (&mut self) {
+        self.bit = self.bit.div_ceil(8) * 8;
+    }
+}
+
+// Canonical Huffman code as the number of codes of each length and the symbols in
+// code order.
+struct Huffman {
+    counts: [usize; 16],
+    symbols: Vec<usize>,
+}
+
+impl Huffman {
+    fn new (lengths: &[usize]) -> Self {
+        let mut counts = [0; 16];
+        for &length in lengths {
+            counts[length] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0; 16];
+        for length in 1..16 {
+            offsets[length] = offsets[length - 1] + counts[length - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length > 0 {
+                symbols[offsets[length]] = symbol;
+                offsets[length] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode (&self, reader: &mut BitReader) -> std::io::Result<usize> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for length in 1..16 {
+            code |= reader.bits(1)?;
+            let count = self.counts[length];
+            if code < first + count {
+                return Ok(self.symbols[index + code - first]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("bad Huffman code"))
+    }
+}
+
+const LENGTH_BASE: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [usize; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [usize; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+// order the code length code lengths of a dynamic block come in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// Decompresses a zlib stream, after RFC 1950 and 1951.
+fn inflate (zlib: &[u8]) -> std::io::Result<Vec<u8>> {
+    if zlib.len() < 2 || zlib[0] & 0x0f != 8 || (u16::from(zlib[0]) << 8 | u16::from(zlib[1])) % 31 != 0 {
+        return Err(invalid("bad zlib header"));
+    }
+    let mut reader = BitReader { data: &zlib[2..], bit: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let length = reader.bits(16)?;
+                if reader.bits(16)? != !length & 0xffff {
+                    return Err(invalid("bad stored block"));
+                }
+                for _ in 0..length {
+                    out.push(reader.bits(8)? as u8);
+                }
+            }
+            kind @ (1 | 2) => {
+                let (literals, distances) = if kind == 1 {
+                    let mut lengths = [8; 288];
+                    lengths[144..256].fill(9);
+                    lengths[256..280].fill(7);
+                    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+                } else {
+                    let literal_count = reader.bits(5)? + 257;
+                    let distance_count = reader.bits(5)? + 1;
+                    let code_count = reader.bits(4)? + 4;
+                    let mut code_lengths = [0; 19];
+                    for &position in CODE_LENGTH_ORDER.iter().take(code_count) {
+                        code_lengths[position] = reader.bits(3)?;
+                    }
+                    let code = Huffman::new(&code_lengths);
+                    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+                    while lengths.len() < literal_count + distance_count {
+                        let (value, repeat) = match code.decode(&mut reader)? {
+                            symbol @ 0..=15 => (symbol, 1),
+                            16 => (*lengths.last().ok_or_else(|| invalid("repeat with nothing before"))?, 3 + reader.bits(2)?),
+                            17 => (0, 3 + reader.bits(3)?),
+                            _ => (0, 11 + reader.bits(7)?),
+                        };
+                        lengths.extend(std::iter::repeat_n(value, repeat));
+                    }
+                    if lengths.len() > literal_count + distance_count {
+                        return Err(invalid("too many code lengths"));
+                    }
+                    (Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..]))
+                };
+                loop {
+                    let symbol = literals.decode(&mut reader)?;
+                    if symbol < 256 {
+                        out.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        break;
+                    }
+                    let i = symbol - 257;
+                    if i >= LENGTH_BASE.len() {
+                        return Err(invalid("bad length"));
+                    }
+                    let length = LENGTH_BASE[i] + reader.bits(LENGTH_EXTRA[i])?;
+                    let j = distances.decode(&mut reader)?;
+                    if j >= DISTANCE_BASE.len() {
+                        return Err(invalid("bad distance"));
+                    }
+                    let distance = DISTANCE_BASE[j] + reader.bits(DISTANCE_EXTRA[j])?;
+                    if distance > out.len() {
+                        return Err(invalid("distance too far back"));
+                    }
+                    let start = out.len() - distance;
+                    for k in 0..length {
+                        out.push(out[start + k]);
+                    }
+                }
+            }
+            _ => return Err(invalid("bad block type")),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn paeth (a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = ((p - i16::from(a)).abs(), (p - i16::from(b)).abs(), (p - i16::from(c)).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// Width, height and RGB values in [0, 1], rows top first, of a non-interlaced PNG of
+// 8 or 16 bits per channel, or 8-bit palette. Alpha is dropped.
+pub fn decode_png (bytes: &[u8]) -> std::io::Result<(usize, usize, Vec<[f64; 3]>)> {
+    if !is_png(bytes) {
+        return Err(invalid("not a PNG file"));
+    }
+    let mut at = SIGNATURE.len();
+    let (mut header, mut palette, mut data) = (None, Vec::new(), Vec::new());
+    while at + 8 <= bytes.len() {
+        let length = u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize;
+        let kind = &bytes[at + 4..at + 8];
+        let body = bytes.get(at + 8..at + 8 + length).ok_or_else(|| invalid("unexpected end of file"))?;
+        match kind {
+            b"IHDR" if length >= 13 => header = Some(body.to_vec()),
+            b"PLTE" => palette = body.to_vec(),
+            b"IDAT" => data.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        at += 12 + length;
+    }
+
+    let header = header.ok_or_else(|| invalid("missing header"))?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let (depth, kind, interlace) = (usize::from(header[8]), header[9], header[12]);
+    let channels = match kind {
+        0 => 1,
+        2 => 3,
+        3 => 1,
+        4 => 2,
+        6 => 4,
+        _ => return Err(invalid("bad colour type")),
+    };
+    if width == 0 || height == 0 || interlace != 0 || !(depth == 8 || depth == 16 && kind != 3) {
+        return Err(invalid("only 8 and 16 bit non-interlaced images are supported"));
+    }
+
+    let raw = inflate(&data)?;
+    let pixel_bytes = channels * depth / 8;
+    let too_large = || invalid("image too large");
+    let stride = width.checked_mul(pixel_bytes).ok_or_else(too_large)?;
+    if raw.len() < height.checked_mul(stride + 1).ok_or_else(too_large)? {
+        return Err(invalid("unexpected end of data"));
+    }
+    let mut rows = vec![0u8; height * stride];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        for x in 0..stride {
+            let a = if x >= pixel_bytes { rows[y * stride + x - pixel_bytes] } else { 0 };
+            let b = if y > 0 { rows[(y - 1) * stride + x] } else { 0 };
+            let c = if x >= pixel_bytes && y > 0 { rows[(y - 1) * stride + x - pixel_bytes] } else { 0 };
+            rows[y * stride + x] = line[x].wrapping_add(match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(invalid("bad filter")),
+            });
+        }
+    }
+
+    let max = if depth == 8 { 255.0 } else { 65535.0 };
+    let sample = |i: usize| match depth {
+        8 => f64::from(rows[i]) / max,
+        _ => f64::from(u16::from(rows[i]) << 8 | u16::from(rows[i + 1])) / max,
+    };
+    let step = depth / 8;
+    (0..width * height)
+        .map(|pixel| {
+            let at = pixel * pixel_bytes;
+            Ok(match kind {
+                0 | 4 => [sample(at); 3],
+                2 | 6 => [sample(at), sample(at + step), sample(at + 2 * step)],
+                _ => {
+                    let entry = usize::from(rows[at]) * 3;
+                    let rgb = palette.get(entry..entry + 3).ok_or_else(|| invalid("colour outside the palette"))?;
+                    [f64::from(rgb[0]) / 255.0, f64::from(rgb[1]) / 255.0, f64::from(rgb[2]) / 255.0]
+                }
+            })
+        })
+        .collect::<std::io::Result<Vec<[f64; 3]>>>()
+        .map(|pixels| (width, height, pixels))
+}
@@ -0,0 +1,84 @@
+use std::f64::consts::PI;
+
+use crate::common::camera::{CameraFrame, CameraModel};
+use crate::common::ray::Ray;
+use crate::one_week::degrees_to_radians;
+
+// Parallel rays over a `width` by `height` window, in scene units, facing along -w.
+pub struct Orthographic {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl CameraModel for Orthographic {
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)> {
+        let origin = frame.origin + frame.local((s - 0.5) * self.width, (t - 0.5) * self.height, 0.0);
+        Some((1.0, Ray::new(&origin, &-frame.w)))
+    }
+}
+
+// Equidistant fisheye: the angle off the axis grows linearly with the distance from the
+// image centre, reaching `fov` / 2 degrees at the top and bottom edges. Corners past
+// that circle see nothing.
+pub struct Fisheye {
+    pub fov: f64,
+    pub aspect_ratio: f64,
+}
+
+impl CameraModel for Fisheye {
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)> {
+        let x = (2.0 * s - 1.0) * self.aspect_ratio;
+        let y = 2.0 * t - 1.0;
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+        let theta = r * degrees_to_radians(self.fov) / 2.0;
+        let phi = y.atan2(x);
+        let direction = frame.local(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos());
+        Some((1.0, Ray::new(&frame.origin, &direction)))
+    }
+}
+
+// Full 360 by 180 degree panorama, longitude across and latitude up, looking along -w
+// in the middle. Meant for a 2:1 image.
+pub struct Equirectangular;
+
+impl CameraModel for Equirectangular {
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)> {
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+        let direction = frame.local(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        );
+        Some((1.0, Ray::new(&frame.origin, &direction)))
+    }
+}
+
+// Six 90 degree faces in a 3x2 grid, +x -x +y on top and -y +z -z below, the axes
+// being u, v and w as in OpenGL cube maps. Meant for a 3:2 image.
+pub struct CubeMap;
+
+impl CameraModel for CubeMap {
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)> {
+        let column = ((s * 3.0) as usize).min(2);
+        let row = if t >= 0.5 { 0 } else { 1 };
+        let x = (s * 3.0 - column as f64) * 2.0 - 1.0;
+        let y = (t * 2.0 - (1 - row) as f64) * 2.0 - 1.0;
+
+        // forward, right and up of each face in camera coordinates
+        let (forward, right, up) = match row * 3 + column {
+            0 => ([1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            1 => ([-1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+            2 => ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            3 => ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            4 => ([0.0, 0.0, 1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            _ => ([0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        };
+        let local = |axis: usize| forward[axis] + right[axis] * x + up[axis] * y;
+        let direction = frame.local(local(0), local(1), local(2));
+        Some((1.0, Ray::new(&frame.origin, &direction)))
+    }
+}
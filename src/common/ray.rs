@@ -1,21 +1,28 @@
 use crate::common::vec3::{Point3, Vec3};
 
+#[derive(Clone, Copy)]
 pub struct Ray {
     pub origin: Point3,
     pub direction: Vec3,
+    // seconds since the shutter opened, carried along the whole path
     pub time: f64
 }
 
 impl Ray {
     pub fn new(origin: &Point3, direction: &Vec3) -> Self {
         Ray {
-            origin: origin.clone(),
-            direction: direction.clone(),
+            origin: *origin,
+            direction: *direction,
             time: 0.0
         }
     }
 
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
-        self.origin.clone() + self.direction.clone() * t
+        self.origin + self.direction * t
     }
 }
\ No newline at end of file
@@ -0,0 +1,359 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::common::aperture::Aperture;
+use crate::common::camera::{CameraFrame, CameraModel};
+use crate::common::ray::Ray;
+use crate::common::vec3::{Point3, Vec3};
+use crate::common::vec3_opts::{dot, unit_vector};
+use crate::one_week::random_f64_01;
+
+// Double Gauss 50mm f/2 lens, after US patent 2,673,491.
+pub const DOUBLE_GAUSS_50MM: &str = "\
+# radius thickness ior aperture
+29.475 3.76 1.67 25.2
+84.83 0.12 1 25.2
+19.275 4.025 1.67 23
+40.77 3.275 1.699 23
+12.75 5.705 1 18
+0 4.5 0 17.1
+-14.495 1.18 1.603 17
+40.77 6.065 1.658 20
+-20.385 0.19 1 20
+437.065 3.22 1.717 20
+-39.73 0 1 20
+";
+
+// Radial bands of the film with their own exit pupil bounds.
+const PUPIL_BANDS: usize = 32;
+// Points per side of the grid over the rear element searched for each band.
+const PUPIL_GRID: usize = 64;
+
+// One spherical surface of a lens prescription, in scene units. `eta` is the index
+// of the glass behind it towards the film, `radius` is zero for the aperture stop.
+#[derive(Clone, Copy)]
+struct LensElement {
+    radius: f64,
+    thickness: f64,
+    eta: f64,
+    aperture_radius: f64,
+}
+
+// Range of the rear element's plane that rays from a band of the film can get through
+// the lens by, for film points along +x. Empty when nothing gets through.
+#[derive(Clone, Copy)]
+struct PupilBounds {
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl PupilBounds {
+    fn area (&self) -> f64 {
+        (self.max.0 - self.min.0).max(0.0) * (self.max.1 - self.min.1).max(0.0)
+    }
+}
+
+// Camera that traces through a real lens system onto a film, which brings the
+// vignetting, distortion and bokeh of that lens. Lens space has the film at z = 0 and
+// the lens towards +z, which becomes -w, the view direction.
+pub struct Realistic {
+    elements: Vec<LensElement>,
+    stop: Aperture,
+    film_width: f64,
+    film_height: f64,
+    pupils: Vec<PupilBounds>,
+    // weight that makes the film's centre as bright as an ideal camera's
+    normalization: f64,
+}
+
+fn invalid (msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("lens: {}", msg))
+}
+
+fn refract (d: &Vec3, n: &Vec3, eta_ratio: f64) -> Option<Vec3> {
+    let cos_i = -dot(d, n);
+    let sin2_t = eta_ratio * eta_ratio * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(*d * eta_ratio + *n * (eta_ratio * cos_i - cos_t))
+}
+
+impl Realistic {
+    // Reads a prescription in the usual text layout: one surface per line from the
+    // front of the lens to the back, giving curvature radius, thickness, index of
+    // refraction and aperture diameter in millimetres, a zero radius marking the
+    // stop. Lines starting with '#' are comments. Scene units are taken as metres.
+    // The film, `film_diagonal` millimetres across, is moved until `focus_distance`
+    // is sharp, and the stop is shrunk to `stop_diameter` millimetres if given.
+    pub fn load (
+        path: &Path,
+        film_diagonal: f64,
+        aspect_ratio: f64,
+        focus_distance: f64,
+        stop_diameter: Option<f64>,
+        stop: Aperture,
+    ) -> std::io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::from_prescription(&text, film_diagonal, aspect_ratio, focus_distance, stop_diameter, stop)
+    }
+
+    pub fn from_prescription (
+        text: &str,
+        film_diagonal: f64,
+        aspect_ratio: f64,
+        focus_distance: f64,
+        stop_diameter: Option<f64>,
+        stop: Aperture,
+    ) -> std::io::Result<Self> {
+        let mut elements = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let numbers = line
+                .split_whitespace()
+                .map(|token| token.parse::<f64>().map_err(|_| invalid("bad number")))
+                .collect::<std::io::Result<Vec<f64>>>()?;
+            if numbers.len() != 4 {
+                return Err(invalid("expected radius, thickness, ior and aperture on each line"));
+            }
+            let mut diameter = numbers[3];
+            if numbers[0] == 0.0 {
+                if let Some(stop_diameter) = stop_diameter {
+                    diameter = diameter.min(stop_diameter);
+                }
+            }
+            elements.push(LensElement {
+                radius: numbers[0] * 0.001,
+                thickness: numbers[1] * 0.001,
+                eta: if numbers[2] == 0.0 { 1.0 } else { numbers[2] },
+                aperture_radius: diameter * 0.001 / 2.0,
+            });
+        }
+        if elements.is_empty() {
+            return Err(invalid("no elements"));
+        }
+
+        let diagonal = film_diagonal * 0.001;
+        let film_height = diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+        let mut camera = Self {
+            elements,
+            stop,
+            film_width: film_height * aspect_ratio,
+            film_height,
+            pupils: Vec::new(),
+            normalization: 1.0,
+        };
+        camera.focus(focus_distance)?;
+        camera.find_pupils();
+        Ok(camera)
+    }
+
+    // Distance from the rear principal plane to where rays coming in parallel to the
+    // axis meet, found by sending one backwards from the film side.
+    pub fn focal_length (&self) -> f64 {
+        let height = self.rear_radius() * 0.01;
+        match self.trace_from_film(Point3::new(height, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)) {
+            Some((_, _, d)) if d.x < 0.0 => height * d.z / -d.x,
+            _ => f64::INFINITY,
+        }
+    }
+
+    // Stops the lens down, or up as far as its stop goes, to the focal length over
+    // `f_number`. The stop is taken as the entrance pupil, which it is close to for
+    // lenses about as symmetric as a double Gauss.
+    pub fn with_f_number (mut self, f_number: f64) -> Self {
+        let radius = self.focal_length() / f_number / 2.0;
+        if let Some(stop) = self.elements.iter_mut().find(|element| element.radius == 0.0) {
+            stop.aperture_radius = stop.aperture_radius.min(radius);
+        }
+        self.find_pupils();
+        self
+    }
+
+    fn find_pupils (&mut self) {
+        self.pupils = (0..PUPIL_BANDS).map(|band| self.pupil_bounds(band)).collect();
+        self.normalization = 1.0 / self.centre_irradiance();
+    }
+
+    fn rear_z (&self) -> f64 {
+        self.elements[self.elements.len() - 1].thickness
+    }
+
+    fn rear_radius (&self) -> f64 {
+        self.elements[self.elements.len() - 1].aperture_radius
+    }
+
+    fn half_diagonal (&self) -> f64 {
+        (self.film_width * self.film_width + self.film_height * self.film_height).sqrt() / 2.0
+    }
+
+    // Follows a lens space ray from the film out of the front of the lens, with the
+    // fraction of its light the stop lets through.
+    fn trace_from_film (&self, origin: Point3, direction: Vec3) -> Option<(f64, Point3, Vec3)> {
+        let mut o = origin;
+        let mut d = unit_vector(&direction);
+        let mut transmission = 1.0;
+        let mut z = 0.0;
+        for i in (0..self.elements.len()).rev() {
+            let element = &self.elements[i];
+            z += element.thickness;
+
+            let t = if element.radius == 0.0 {
+                if d.z <= 0.0 {
+                    return None;
+                }
+                (z - o.z) / d.z
+            } else {
+                let center = Point3::new(0.0, 0.0, z - element.radius);
+                let oc = o - center;
+                let b = dot(&oc, &d);
+                let discriminant = b * b - (oc.length_squared() - element.radius * element.radius);
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let root = discriminant.sqrt();
+                // the surface near the vertex, not the far side of its sphere
+                if (d.z > 0.0) == (element.radius < 0.0) { -b - root } else { -b + root }
+            };
+            if t <= 0.0 {
+                return None;
+            }
+            let p = o + d * t;
+            let r2 = p.x * p.x + p.y * p.y;
+            if r2 > element.aperture_radius * element.aperture_radius {
+                return None;
+            }
+
+            if element.radius == 0.0 {
+                transmission *= self.stop.transmission(p.x / element.aperture_radius, p.y / element.aperture_radius);
+                if transmission <= 0.0 {
+                    return None;
+                }
+            } else {
+                let center = Point3::new(0.0, 0.0, z - element.radius);
+                let mut n = (p - center) / element.radius.abs();
+                if dot(&n, &d) > 0.0 {
+                    n = -n;
+                }
+                let eta_front = if i > 0 { self.elements[i - 1].eta } else { 1.0 };
+                d = unit_vector(&refract(&d, &n, element.eta / eta_front)?);
+            }
+            o = p;
+        }
+        Some((transmission, o, d))
+    }
+
+    // Where a near axial ray from the film's centre crosses the axis after leaving the
+    // lens, infinitely far when it leaves parallel or spreading out.
+    fn axial_focus (&self) -> Option<f64> {
+        let (_, o, d) = self.trace_from_film(Point3::default(), Vec3::new(0.001, 0.0, 1.0))?;
+        if d.x < 0.0 { Some(o.z - o.x / d.x * d.z) } else { Some(f64::INFINITY) }
+    }
+
+    // Moves the film until a point on the axis `distance` from it is sharp, bisecting
+    // on the gap between the film and the rear element.
+    fn focus (&mut self, distance: f64) -> std::io::Result<()> {
+        let last = self.elements.len() - 1;
+        let (mut near, mut far) = (0.0, 1e-3);
+        loop {
+            self.elements[last].thickness = far;
+            match self.axial_focus() {
+                Some(z) if z < distance => break,
+                Some(_) if far < 10.0 => far *= 2.0,
+                _ => return Err(invalid("can't focus at that distance")),
+            }
+        }
+        for _ in 0..64 {
+            let film = (near + far) / 2.0;
+            self.elements[last].thickness = film;
+            match self.axial_focus() {
+                Some(z) if z < distance => far = film,
+                _ => near = film,
+            }
+        }
+        self.elements[last].thickness = far;
+        Ok(())
+    }
+
+    fn pupil_bounds (&self, band: usize) -> PupilBounds {
+        let r0 = band as f64 / PUPIL_BANDS as f64 * self.half_diagonal();
+        let r1 = (band + 1) as f64 / PUPIL_BANDS as f64 * self.half_diagonal();
+        let extent = self.rear_radius() * 1.5;
+        let step = 2.0 * extent / PUPIL_GRID as f64;
+
+        let mut bounds = PupilBounds { min: (f64::INFINITY, f64::INFINITY), max: (f64::NEG_INFINITY, f64::NEG_INFINITY) };
+        for film_x in [r0, (r0 + r1) / 2.0, r1] {
+            let film = Point3::new(film_x, 0.0, 0.0);
+            for j in 0..=PUPIL_GRID {
+                for i in 0..=PUPIL_GRID {
+                    let (x, y) = (-extent + i as f64 * step, -extent + j as f64 * step);
+                    let rear = Point3::new(x, y, self.rear_z());
+                    if self.trace_from_film(film, rear - film).is_some() {
+                        bounds.min = (bounds.min.0.min(x), bounds.min.1.min(y));
+                        bounds.max = (bounds.max.0.max(x), bounds.max.1.max(y));
+                    }
+                }
+            }
+        }
+        // grid points catch the pupil only to within a step
+        bounds.min = (bounds.min.0 - step, bounds.min.1 - step);
+        bounds.max = (bounds.max.0 + step, bounds.max.1 + step);
+        bounds
+    }
+
+    // A point on the rear element's plane within the pupil for `film`, and the area
+    // it was drawn from. None when no light reaches that part of the film.
+    fn sample_pupil (&self, film: &Point3) -> Option<(Point3, f64)> {
+        let r = (film.x * film.x + film.y * film.y).sqrt();
+        let band = ((r / self.half_diagonal() * PUPIL_BANDS as f64) as usize).min(PUPIL_BANDS - 1);
+        let bounds = &self.pupils[band];
+        if bounds.area() == 0.0 {
+            return None;
+        }
+        let x = bounds.min.0 + random_f64_01() * (bounds.max.0 - bounds.min.0);
+        let y = bounds.min.1 + random_f64_01() * (bounds.max.1 - bounds.min.1);
+        // the bounds were found along +x, turn them to where the film point is
+        let (sin, cos) = if r > 0.0 { (film.y / r, film.x / r) } else { (0.0, 1.0) };
+        Some((Point3::new(x * cos - y * sin, x * sin + y * cos, self.rear_z()), bounds.area()))
+    }
+
+    // Irradiance on the film per unit of radiance, cos⁴ falloff included, for a ray
+    // from `film` through `rear` that made it out with `transmission`.
+    fn irradiance (&self, film: &Point3, rear: &Point3, transmission: f64, area: f64) -> f64 {
+        let cos_theta = unit_vector(&(*rear - *film)).z;
+        transmission * cos_theta.powi(4) * area / (self.rear_z() * self.rear_z())
+    }
+
+    // Mean irradiance at the film's centre, over the same grid the pupils were found on.
+    fn centre_irradiance (&self) -> f64 {
+        let bounds = &self.pupils[0];
+        if bounds.area() == 0.0 {
+            return 1.0;
+        }
+        let (mut total, mut count) = (0.0, 0);
+        for j in 0..PUPIL_GRID {
+            for i in 0..PUPIL_GRID {
+                let x = bounds.min.0 + (i as f64 + 0.5) / PUPIL_GRID as f64 * (bounds.max.0 - bounds.min.0);
+                let y = bounds.min.1 + (j as f64 + 0.5) / PUPIL_GRID as f64 * (bounds.max.1 - bounds.min.1);
+                let rear = Point3::new(x, y, self.rear_z());
+                if let Some((transmission, _, _)) = self.trace_from_film(Point3::default(), rear) {
+                    total += self.irradiance(&Point3::default(), &rear, transmission, bounds.area());
+                }
+                count += 1;
+            }
+        }
+        if total > 0.0 { total / count as f64 } else { 1.0 }
+    }
+}
+
+impl CameraModel for Realistic {
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)> {
+        // the lens turns the image upside down
+        let film = Point3::new(-(s - 0.5) * self.film_width, -(t - 0.5) * self.film_height, 0.0);
+        let (rear, area) = self.sample_pupil(&film)?;
+        let (transmission, o, d) = self.trace_from_film(film, rear - film)?;
+        let weight = self.irradiance(&film, &rear, transmission, area) * self.normalization;
+        Some((weight, Ray::new(&(frame.origin + frame.local(o.x, o.y, -o.z)), &frame.local(d.x, d.y, -d.z))))
+    }
+}
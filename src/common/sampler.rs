@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Every random number of the renderer comes from `next_f64`. Each thread draws from
+// its own generator, seeded from `set_seed` if it was called before the thread's
+// first draw. While a `PrimarySampler` is installed with `with_primary` it answers
+// instead, so the Metropolis integrator can mutate the numbers a path was built from.
+
+static SEEDED: AtomicBool = AtomicBool::new(false);
+static SEED: AtomicU64 = AtomicU64::new(0);
+static THREADS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(thread_rng());
+    static PRIMARY: RefCell<Option<PrimarySampler>> = const { RefCell::new(None) };
+}
+
+fn thread_rng () -> StdRng {
+    let thread = THREADS.fetch_add(1, Ordering::Relaxed);
+    if SEEDED.load(Ordering::Relaxed) {
+        StdRng::seed_from_u64(mix(SEED.load(Ordering::Relaxed), thread))
+    } else {
+        StdRng::from_entropy()
+    }
+}
+
+// SplitMix64 finaliser, spreads nearby seeds over unrelated streams.
+pub fn mix (seed: u64, stream: u64) -> u64 {
+    let mut z = seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+pub fn set_seed (seed: u64) {
+    SEED.store(seed, Ordering::Relaxed);
+    SEEDED.store(true, Ordering::Relaxed);
+    RNG.with(|rng| *rng.borrow_mut() = thread_rng());
+}
+
+pub fn next_f64 () -> f64 {
+    let primary = PRIMARY.with(|primary| primary.borrow_mut().as_mut().map(|sampler| sampler.next()));
+    match primary {
+        Some(value) => value,
+        None => RNG.with(|rng| rng.borrow_mut().gen()),
+    }
+}
+
+// Runs `f` with every `next_f64` on this thread answered by `sampler`.
+pub fn with_primary<R, F: FnOnce() -> R> (sampler: PrimarySampler, f: F) -> (PrimarySampler, R) {
+    PRIMARY.with(|primary| *primary.borrow_mut() = Some(sampler));
+    let result = f();
+    let sampler = PRIMARY.with(|primary| primary.borrow_mut().take()).unwrap();
+    (sampler, result)
+}
+
+#[derive(Clone, Copy, Default)]
+struct PrimarySample {
+    value: f64,
+    last_modified: u64,
+    value_backup: f64,
+    modify_backup: u64,
+}
+
+// A point in primary sample space, the unit hypercube of random numbers a path is
+// built from (after pbrt's MLTSampler). Dimensions are created on first use and
+// mutated lazily, so paths may consume any number of them.
+pub struct PrimarySampler {
+    rng: StdRng,
+    sigma: f64,
+    large_step_probability: f64,
+    samples: Vec<PrimarySample>,
+    iteration: u64,
+    large_step: bool,
+    last_large_step: u64,
+    index: usize,
+}
+
+impl PrimarySampler {
+    // The first iteration is always a large step, so equal seeds give equal paths.
+    pub fn new (seed: u64, sigma: f64, large_step_probability: f64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            sigma,
+            large_step_probability,
+            samples: Vec::new(),
+            iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+            index: 0,
+        }
+    }
+
+    pub fn start_iteration (&mut self) {
+        self.iteration += 1;
+        self.large_step = self.iteration == 1 || self.rng.gen::<f64>() < self.large_step_probability;
+        self.index = 0;
+    }
+
+    pub fn accept (&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    pub fn reject (&mut self) {
+        for sample in self.samples.iter_mut() {
+            if sample.last_modified == self.iteration {
+                sample.value = sample.value_backup;
+                sample.last_modified = sample.modify_backup;
+            }
+        }
+        self.iteration -= 1;
+    }
+
+    // Random number for the Metropolis acceptance test, outside the path's dimensions.
+    pub fn uniform (&mut self) -> f64 {
+        self.rng.gen()
+    }
+
+    fn next (&mut self) -> f64 {
+        let index = self.index;
+        self.index += 1;
+        if index >= self.samples.len() {
+            self.samples.resize(index + 1, PrimarySample::default());
+        }
+
+        let mut sample = self.samples[index];
+        // Catch up on the large step this dimension missed while unused.
+        if sample.last_modified < self.last_large_step {
+            sample.value = self.rng.gen();
+            sample.last_modified = self.last_large_step;
+        }
+
+        sample.value_backup = sample.value;
+        sample.modify_backup = sample.last_modified;
+        if self.large_step {
+            sample.value = self.rng.gen();
+        } else {
+            // Small steps missed while unused add up to one wider Gaussian step.
+            let steps = (self.iteration - sample.last_modified) as f64;
+            let sigma = self.sigma * steps.sqrt();
+            let u1: f64 = 1.0 - self.rng.gen::<f64>();
+            let u2: f64 = self.rng.gen();
+            let normal = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+            sample.value += normal * sigma;
+            sample.value -= sample.value.floor();
+        }
+        sample.last_modified = self.iteration;
+
+        self.samples[index] = sample;
+        sample.value
+    }
+}
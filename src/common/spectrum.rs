@@ -0,0 +1,205 @@
+use std::ops::{AddAssign, Div, Mul, MulAssign};
+
+use crate::common::vec3::Color;
+
+// Visible range sampled by the spectral renderer, in nanometres.
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 720.0;
+
+// Wavelengths carried by each path: the hero and its evenly spaced companions.
+pub const WAVELENGTHS: usize = 4;
+
+pub struct Wavelengths {
+    pub lambda: [f64; WAVELENGTHS],
+    pub pdf: [f64; WAVELENGTHS],
+}
+
+impl Wavelengths {
+    // Hero wavelength sampling (Wilkie et al.): `u` places the hero uniformly and the
+    // others follow at equal steps, wrapping around the range.
+    pub fn sample (u: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let mut lambda = [0.0; WAVELENGTHS];
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let offset = (u + i as f64 / WAVELENGTHS as f64).fract();
+            *l = LAMBDA_MIN + offset * range;
+        }
+        Self { lambda, pdf: [1.0 / range; WAVELENGTHS] }
+    }
+
+    pub fn hero (&self) -> f64 {
+        self.lambda[0]
+    }
+
+    // After a wavelength dependent direction only the hero's path is valid.
+    pub fn terminate_secondary (&mut self) {
+        if self.pdf[1] == 0.0 {
+            return;
+        }
+        for pdf in self.pdf.iter_mut().skip(1) {
+            *pdf = 0.0;
+        }
+        self.pdf[0] /= WAVELENGTHS as f64;
+    }
+}
+
+// A spectral quantity at the path's wavelengths.
+#[derive(Clone, Copy)]
+pub struct Spectrum {
+    pub values: [f64; WAVELENGTHS],
+}
+
+impl Spectrum {
+    pub fn constant (value: f64) -> Self {
+        Self { values: [value; WAVELENGTHS] }
+    }
+
+    pub fn max_value (&self) -> f64 {
+        self.values.iter().cloned().fold(0.0, f64::max)
+    }
+
+    // Smits' (1999) smooth spectrum for an RGB triple, linear in its magnitude so
+    // emitted radiance above one works as well as albedos.
+    pub fn from_rgb (rgb: &Color, lambdas: &Wavelengths) -> Self {
+        let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+        let terms: [(f64, &[f64; 10]); 3] = if r <= g && r <= b {
+            if g <= b {
+                [(r, &WHITE), (g - r, &CYAN), (b - g, &BLUE)]
+            } else {
+                [(r, &WHITE), (b - r, &CYAN), (g - b, &GREEN)]
+            }
+        } else if g <= r && g <= b {
+            if r <= b {
+                [(g, &WHITE), (r - g, &MAGENTA), (b - r, &BLUE)]
+            } else {
+                [(g, &WHITE), (b - g, &MAGENTA), (r - b, &RED)]
+            }
+        } else if r <= g {
+            [(b, &WHITE), (r - b, &YELLOW), (g - r, &GREEN)]
+        } else {
+            [(b, &WHITE), (g - b, &YELLOW), (r - g, &RED)]
+        };
+
+        let mut values = [0.0; WAVELENGTHS];
+        for (value, lambda) in values.iter_mut().zip(lambdas.lambda.iter()) {
+            *value = terms.iter().map(|(weight, basis)| weight * smits_basis(basis, *lambda)).sum();
+        }
+        Self { values }
+    }
+
+    // Linear sRGB of the Monte Carlo estimate at `lambdas`, scaled so a constant
+    // spectrum of one comes out white.
+    pub fn to_rgb (self, lambdas: &Wavelengths) -> Color {
+        let mut xyz = Color::default();
+        for i in 0..WAVELENGTHS {
+            if lambdas.pdf[i] > 0.0 {
+                xyz += cie_xyz(lambdas.lambda[i]) * (self.values[i] / lambdas.pdf[i]);
+            }
+        }
+        let rgb = xyz_to_srgb(&(xyz / WAVELENGTHS as f64));
+        let white = equal_energy_white();
+        Color::new(rgb.x / white.x, rgb.y / white.y, rgb.z / white.z)
+    }
+}
+
+impl Mul for Spectrum {
+    type Output = Spectrum;
+
+    fn mul (mut self, rhs: Spectrum) -> Spectrum {
+        self *= rhs;
+        self
+    }
+}
+
+impl MulAssign for Spectrum {
+    fn mul_assign (&mut self, rhs: Spectrum) {
+        for (a, b) in self.values.iter_mut().zip(rhs.values.iter()) {
+            *a *= b;
+        }
+    }
+}
+
+impl MulAssign<f64> for Spectrum {
+    fn mul_assign (&mut self, rhs: f64) {
+        for a in self.values.iter_mut() {
+            *a *= rhs;
+        }
+    }
+}
+
+impl Div<f64> for Spectrum {
+    type Output = Spectrum;
+
+    fn div (mut self, rhs: f64) -> Spectrum {
+        self *= 1.0 / rhs;
+        self
+    }
+}
+
+impl AddAssign for Spectrum {
+    fn add_assign (&mut self, rhs: Spectrum) {
+        for (a, b) in self.values.iter_mut().zip(rhs.values.iter()) {
+            *a += b;
+        }
+    }
+}
+
+// Smits' basis spectra, ten bins evenly covering 380 nm to 720 nm.
+const WHITE: [f64; 10] = [1.0, 1.0, 0.9999, 0.9993, 0.9992, 0.9998, 1.0, 1.0, 1.0, 1.0];
+const CYAN: [f64; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0, 0.0, 0.0];
+const MAGENTA: [f64; 10] = [1.0, 1.0, 0.9685, 0.2229, 0.0, 0.0458, 0.8369, 1.0, 1.0, 0.9959];
+const YELLOW: [f64; 10] = [0.0001, 0.0, 0.1088, 0.6651, 1.0, 1.0, 0.9996, 0.9586, 0.9685, 0.9840];
+const RED: [f64; 10] = [0.1012, 0.0515, 0.0, 0.0, 0.0, 0.0, 0.8325, 1.0149, 1.0149, 1.0149];
+const GREEN: [f64; 10] = [0.0, 0.0, 0.0273, 0.7937, 1.0, 0.9418, 0.1719, 0.0, 0.0, 0.0025];
+const BLUE: [f64; 10] = [1.0, 1.0, 0.8916, 0.3323, 0.0, 0.0, 0.0003, 0.0369, 0.0483, 0.0496];
+
+// Linear interpolation between bin centres.
+fn smits_basis (basis: &[f64; 10], lambda: f64) -> f64 {
+    let x = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN) * 10.0 - 0.5;
+    if x <= 0.0 {
+        return basis[0];
+    }
+    if x >= 9.0 {
+        return basis[9];
+    }
+    let i = x as usize;
+    let t = x - i as f64;
+    basis[i] * (1.0 - t) + basis[i + 1] * t
+}
+
+// CIE 1931 colour matching functions, multi-lobe fit of Wyman, Sloan and Shirley (2013).
+fn cie_xyz (lambda: f64) -> Color {
+    let g = |mu: f64, sigma_lo: f64, sigma_hi: f64| {
+        let t = (lambda - mu) / if lambda < mu { sigma_lo } else { sigma_hi };
+        (-0.5 * t * t).exp()
+    };
+    Color::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+fn xyz_to_srgb (xyz: &Color) -> Color {
+    Color::new(
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    )
+}
+
+// Linear sRGB of a constant spectrum of one, integrated by the midpoint rule.
+fn equal_energy_white () -> Color {
+    thread_local! {
+        static WHITE_RGB: Color = {
+            let steps = 340;
+            let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+            let mut xyz = Color::default();
+            for i in 0..steps {
+                xyz += cie_xyz(LAMBDA_MIN + (i as f64 + 0.5) * step) * step;
+            }
+            xyz_to_srgb(&xyz)
+        };
+    }
+    WHITE_RGB.with(|white| *white)
+}
@@ -0,0 +1,144 @@
+use std::cell::Cell;
+use std::ops::AddAssign;
+use std::thread::LocalKey;
+
+// Work done tracing rays, counted per thread as it happens and gathered up by the
+// renderer. `rays` counts every closest hit query, camera rays among them.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RayStats {
+    pub primary_rays: u64,
+    pub rays: u64,
+    pub shadow_rays: u64,
+    pub node_visits: u64,
+    pub intersection_tests: u64,
+}
+
+// A counter each, so that counting is one add in the hot loops.
+thread_local! {
+    static PRIMARY_RAYS: Cell<u64> = const { Cell::new(0) };
+    static RAYS: Cell<u64> = const { Cell::new(0) };
+    static SHADOW_RAYS: Cell<u64> = const { Cell::new(0) };
+    static NODE_VISITS: Cell<u64> = const { Cell::new(0) };
+    static INTERSECTION_TESTS: Cell<u64> = const { Cell::new(0) };
+}
+
+fn count (counter: &'static LocalKey<Cell<u64>>) {
+    counter.with(|count| count.set(count.get() + 1));
+}
+
+pub fn primary_ray () {
+    count(&PRIMARY_RAYS);
+}
+
+pub fn ray () {
+    count(&RAYS);
+}
+
+pub fn shadow_ray () {
+    count(&SHADOW_RAYS);
+}
+
+pub fn node_visit () {
+    count(&NODE_VISITS);
+}
+
+pub fn intersection_test () {
+    count(&INTERSECTION_TESTS);
+}
+
+fn read (get: impl Fn(&'static LocalKey<Cell<u64>>) -> u64) -> RayStats {
+    RayStats {
+        primary_rays: get(&PRIMARY_RAYS),
+        rays: get(&RAYS),
+        shadow_rays: get(&SHADOW_RAYS),
+        node_visits: get(&NODE_VISITS),
+        intersection_tests: get(&INTERSECTION_TESTS),
+    }
+}
+
+// This thread's counters so far.
+pub fn current () -> RayStats {
+    read(|counter| counter.with(Cell::get))
+}
+
+// This thread's counters since the last call.
+pub fn take () -> RayStats {
+    read(|counter| counter.with(|count| count.replace(0)))
+}
+
+impl AddAssign for RayStats {
+    fn add_assign (&mut self, other: RayStats) {
+        self.primary_rays += other.primary_rays;
+        self.rays += other.rays;
+        self.shadow_rays += other.shadow_rays;
+        self.node_visits += other.node_visits;
+        self.intersection_tests += other.intersection_tests;
+    }
+}
+
+impl RayStats {
+    pub fn secondary_rays (&self) -> u64 {
+        self.rays.saturating_sub(self.primary_rays)
+    }
+
+    pub fn total_rays (&self) -> u64 {
+        self.rays + self.shadow_rays
+    }
+
+    // Segments traced per camera ray, shadow rays aside.
+    pub fn average_path_length (&self) -> f64 {
+        self.rays as f64 / self.primary_rays.max(1) as f64
+    }
+
+    pub fn node_visits_per_ray (&self) -> f64 {
+        self.node_visits as f64 / self.total_rays().max(1) as f64
+    }
+
+    pub fn tests_per_ray (&self) -> f64 {
+        self.intersection_tests as f64 / self.total_rays().max(1) as f64
+    }
+
+    pub fn rays_per_second (&self, seconds: f64) -> f64 {
+        if seconds > 0.0 { self.total_rays() as f64 / seconds } else { 0.0 }
+    }
+
+    // A few lines for the end of a render.
+    pub fn summary (&self, seconds: f64) -> String {
+        format!(
+            "rendered in {:.2}s, {:.2}M rays/s\n\
+             rays: {} primary, {} secondary, {} shadow\n\
+             average path length: {:.2}\n\
+             BVH node visits: {} ({:.1} per ray)\n\
+             intersection tests: {} ({:.1} per ray)",
+            seconds,
+            self.rays_per_second(seconds) / 1e6,
+            self.primary_rays,
+            self.secondary_rays(),
+            self.shadow_rays,
+            self.average_path_length(),
+            self.node_visits,
+            self.node_visits_per_ray(),
+            self.intersection_tests,
+            self.tests_per_ray(),
+        )
+    }
+
+    // One flat JSON object, for dashboards.
+    pub fn to_json (&self, seconds: f64) -> String {
+        format!(
+            "{{\n  \"seconds\": {:.3},\n  \"primary_rays\": {},\n  \"secondary_rays\": {},\n  \"shadow_rays\": {},\n  \
+             \"rays_per_second\": {:.0},\n  \"average_path_length\": {:.4},\n  \"bvh_node_visits\": {},\n  \
+             \"node_visits_per_ray\": {:.4},\n  \"intersection_tests\": {},\n  \"intersection_tests_per_ray\": {:.4}\n}}\n",
+            seconds,
+            self.primary_rays,
+            self.secondary_rays(),
+            self.shadow_rays,
+            self.rays_per_second(seconds),
+            self.average_path_length(),
+            self.node_visits,
+            self.node_visits_per_ray(),
+            self.intersection_tests,
+            self.tests_per_ray(),
+        )
+    }
+}
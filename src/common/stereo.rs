@@ -0,0 +1,98 @@
+use std::f64::consts::PI;
+use std::io::{Error, ErrorKind};
+
+use crate::common::camera::{CameraFrame, CameraModel, Perspective};
+use crate::common::ray::Ray;
+
+// Where each eye's view goes in the single output image.
+#[derive(Clone, Copy)]
+pub enum StereoLayout {
+    // left eye in the left half
+    SideBySide,
+    // left eye in the top half
+    OverUnder,
+}
+
+impl StereoLayout {
+    // Which eye sees film position (s, t), and where on its own film.
+    fn split (&self, s: f64, t: f64) -> (bool, f64, f64) {
+        match self {
+            StereoLayout::SideBySide if s < 0.5 => (true, s * 2.0, t),
+            StereoLayout::SideBySide => (false, s * 2.0 - 1.0, t),
+            StereoLayout::OverUnder if t >= 0.5 => (true, s, t * 2.0 - 1.0),
+            StereoLayout::OverUnder => (false, s, t * 2.0),
+        }
+    }
+}
+
+// Two views `interaxial` apart along the frame's u axis, packed into one image.
+pub struct Stereo {
+    pub left: Box<dyn CameraModel>,
+    pub right: Box<dyn CameraModel>,
+    pub layout: StereoLayout,
+    pub interaxial: f64,
+}
+
+impl Stereo {
+    pub fn new (left: Box<dyn CameraModel>, right: Box<dyn CameraModel>, layout: StereoLayout, interaxial: f64) -> Self {
+        Self { left, right, layout, interaxial }
+    }
+
+    // Parallel perspective eyes whose films slide towards each other so that things
+    // `convergence` away land in the same place in both views. Unlike toeing the
+    // cameras in, this keeps vertical parallax out. `convergence` has to be positive.
+    pub fn perspective (
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+        layout: StereoLayout,
+        interaxial: f64,
+        convergence: f64,
+    ) -> std::io::Result<Self> {
+        if convergence.is_nan() || convergence <= 0.0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "stereo: convergence must be a positive distance"));
+        }
+        let eye = || Perspective::new(vfov, aspect_ratio, aperture, focus_dist);
+        let shift = interaxial / (2.0 * convergence * eye().viewport_width());
+        Ok(Self::new(Box::new(eye().with_shift(shift, 0.0)), Box::new(eye().with_shift(-shift, 0.0)), layout, interaxial))
+    }
+}
+
+impl CameraModel for Stereo {
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)> {
+        let (left, s, t) = self.layout.split(s, t);
+        let (model, side) = if left { (&self.left, -0.5) } else { (&self.right, 0.5) };
+        let eye = CameraFrame {
+            origin: frame.origin + frame.u * (side * self.interaxial),
+            ..*frame
+        };
+        model.get_ray(&eye, s, t)
+    }
+}
+
+// Omni-directional stereo panorama for VR headsets: an equirectangular view per eye,
+// left over right, each ray leaving from the point on a circle `interaxial` across
+// where an eye would be when looking that way. The offset fades towards the poles,
+// where the eyes would otherwise swap over.
+pub struct Ods {
+    pub interaxial: f64,
+}
+
+impl CameraModel for Ods {
+    fn get_ray (&self, frame: &CameraFrame, s: f64, t: f64) -> Option<(f64, Ray)> {
+        let (left, s, t) = StereoLayout::OverUnder.split(s, t);
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+        let side = if left { -0.5 } else { 0.5 };
+
+        let right = frame.local(longitude.cos(), 0.0, longitude.sin());
+        let origin = frame.origin + right * (side * self.interaxial * latitude.cos());
+        let direction = frame.local(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        );
+        Some((1.0, Ray::new(&origin, &direction)))
+    }
+}
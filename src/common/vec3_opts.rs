@@ -1,7 +1,6 @@
-use std::ops::{Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign, Neg};
+use std::ops::{Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign, Neg, Index};
 use crate::common::vec3::Vec3;
 use crate::one_week::random_f64;
-use std::cmp::min;
 
 impl Add<Vec3> for Vec3 {
     type Output = Self;
@@ -111,6 +110,18 @@ impl DivAssign<f64> for Vec3 {
     }
 }
 
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            _ => &self.z
+        }
+    }
+}
+
 pub fn dot(u: &Vec3, v: &Vec3) -> f64 {
     u.x * v.x + u.y * v.y + u.z * v.z
 }
@@ -157,7 +168,7 @@ pub fn random_unit_vector() -> Vec3 {
 
 pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
     let in_unit_sphere = random_in_unit_sphere();
-    if dot(&in_unit_sphere, &normal) > 0.0 {
+    if dot(&in_unit_sphere, normal) > 0.0 {
         in_unit_sphere
     } else {
         -in_unit_sphere
@@ -165,12 +176,12 @@ pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
 }
 
 pub fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
-    v.clone() - *n * dot(v, n) * 2.0
+    *v - *n * dot(v, n) * 2.0
 }
 
 pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
     let cos_theta = dot(&-*uv, n).min(1.0);
-    let r_out_perp = (uv.clone() + n.clone() * cos_theta) * etai_over_etat;
-    let r_out_parallel = n.clone() * -(1.0 - r_out_perp.length_squared()).abs().sqrt();
+    let r_out_perp = (*uv + *n * cos_theta) * etai_over_etat;
+    let r_out_parallel = *n * -(1.0 - r_out_perp.length_squared()).abs().sqrt();
     r_out_perp + r_out_parallel
 }
@@ -0,0 +1,31 @@
+use crate::common::ray::Ray;
+use crate::common::vec3::Color;
+use crate::common::vec3_opts::{dot, random_in_hemisphere, unit_vector};
+use crate::integrator::Integrator;
+use crate::one_week::scene::Scene;
+
+pub struct AmbientOcclusionIntegrator {
+    pub samples: i32,
+    pub max_distance: f64,
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        let rec = match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => rec,
+            None => return Color::new(1.0, 1.0, 1.0),
+        };
+
+        // Uniform hemisphere directions, weighted by 2cos so an open surface is 1.
+        let mut visibility = 0.0;
+        for _ in 0..self.samples {
+            let direction = unit_vector(&random_in_hemisphere(&rec.normal));
+            if scene.unoccluded(&rec.p, &(rec.p + direction * self.max_distance), r.time) {
+                visibility += 2.0 * dot(&direction, &rec.normal);
+            }
+        }
+        visibility /= f64::from(self.samples);
+
+        Color::new(visibility, visibility, visibility)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::common::ray::Ray;
+use crate::common::sampler;
+use crate::common::vec3::{Color, Point3, Vec3};
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::scene::Scene;
+
+// Auxiliary passes a render can write next to the beauty image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Aov {
+    Albedo,
+    Normal,
+    Depth,
+    Position,
+    Uv,
+    ObjectId,
+    MaterialId,
+    Direct,
+    Indirect,
+    Emission,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 10] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Depth,
+        Aov::Position,
+        Aov::Uv,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::Emission,
+    ];
+
+    pub fn name (self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::Uv => "uv",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Emission => "emission",
+        }
+    }
+
+    pub fn from_name (name: &str) -> Option<Aov> {
+        Aov::ALL.iter().copied().find(|aov| aov.name() == name)
+    }
+
+    // Lighting passes add up to the beauty image and are exposed like it.
+    pub fn is_lighting (self) -> bool {
+        matches!(self, Aov::Direct | Aov::Indirect | Aov::Emission)
+    }
+}
+
+// Stable pseudo-random colour for an id, for passes that tell things apart.
+pub fn id_color (id: usize) -> Color {
+    let hash = sampler::mix(id as u64, 0);
+    Color::new(
+        (hash & 0xff) as f64 / 255.0,
+        ((hash >> 8) & 0xff) as f64 / 255.0,
+        ((hash >> 16) & 0xff) as f64 / 255.0,
+    )
+}
+
+// What one camera sample saw at its first hit. Albedo is the attenuation of the
+// first scatter, which averages to the surface's reflectance, and the background's
+// radiance where the ray escapes. Depth is the distance along the ray, 0 on a miss.
+// Emission is light seen straight from the camera, direct light arrives after one
+// bounce and indirect after more.
+#[derive(Clone, Copy, Default)]
+pub struct Aovs {
+    pub albedo: Color,
+    pub normal: Vec3,
+    pub depth: f64,
+    pub position: Point3,
+    pub uv: (f64, f64),
+    pub object_id: usize,
+    pub material_id: usize,
+    pub direct: Color,
+    pub indirect: Color,
+    pub emission: Color,
+}
+
+impl Aovs {
+    // Everything but the attenuation and lighting, taken from the first hit of `r`.
+    pub fn at_hit (r: &Ray, rec: &HitRecord, scene: &Scene) -> Self {
+        Self {
+            normal: rec.shading_normal,
+            depth: rec.t * r.direction.length(),
+            position: rec.p,
+            uv: (rec.u, rec.v),
+            object_id: rec.object_id,
+            material_id: scene.material_id(&rec.material),
+            ..Self::default()
+        }
+    }
+
+    // For a camera ray that hits nothing.
+    pub fn at_miss (r: &Ray, scene: &Scene) -> Self {
+        let background = scene.background.radiance(&r.direction);
+        Self { albedo: background, emission: background, ..Self::default() }
+    }
+
+    // Light that reached the camera after `bounces` scattering events.
+    pub fn add_light (&mut self, bounces: i32, light: Color) {
+        match bounces {
+            0 => self.emission += light,
+            1 => self.direct += light,
+            _ => self.indirect += light,
+        }
+    }
+
+    pub fn get (&self, aov: Aov) -> Color {
+        match aov {
+            Aov::Albedo => self.albedo,
+            Aov::Normal => self.normal,
+            Aov::Depth => Color::new(self.depth, self.depth, self.depth),
+            Aov::Position => self.position,
+            Aov::Uv => Color::new(self.uv.0, self.uv.1, 0.0),
+            Aov::ObjectId if self.object_id == 0 => Color::default(),
+            Aov::ObjectId => id_color(self.object_id),
+            Aov::MaterialId if self.material_id == 0 => Color::default(),
+            Aov::MaterialId => id_color(self.material_id),
+            Aov::Direct => self.direct,
+            Aov::Indirect => self.indirect,
+            Aov::Emission => self.emission,
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::common::camera::Camera;
+use crate::common::ray::Ray;
+use crate::common::vec3::{Color, Point3, Vec3};
+use crate::common::vec3_opts::{dot, unit_vector};
+use crate::integrator::{Integrator, Splat};
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::light::Light;
+use crate::one_week::random_f64_01;
+use crate::one_week::scene::Scene;
+
+// Bidirectional path tracer after Veach, with the vertex bookkeeping of pbrt-v3.
+// Every light is a delta light, so strategies that hit a light (s = 0) only exist
+// for the background, which no light subpath can start from and gets weight one.
+pub struct BdptIntegrator {
+    pub camera: Arc<Camera>,
+    pub max_depth: usize,
+}
+
+#[derive(Clone)]
+enum VertexKind {
+    Camera,
+    Light(Arc<dyn Light>),
+    // the hit and the ray that arrived at it
+    Surface(Box<HitRecord>, Ray),
+}
+
+#[derive(Clone)]
+struct Vertex {
+    kind: VertexKind,
+    p: Point3,
+    // zero for camera and light endpoints
+    normal: Vec3,
+    beta: Color,
+    // area densities of sampling this vertex from either end of the path
+    pdf_fwd: f64,
+    pdf_rev: f64,
+    delta: bool,
+}
+
+impl Vertex {
+    fn endpoint (kind: VertexKind, p: Point3, beta: Color) -> Self {
+        Self {
+            kind,
+            p,
+            normal: Vec3::default(),
+            beta,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            delta: false,
+        }
+    }
+
+    fn is_on_surface (&self) -> bool {
+        matches!(self.kind, VertexKind::Surface(..))
+    }
+
+    fn is_infinite_light (&self) -> bool {
+        match &self.kind {
+            VertexKind::Light(light) => light.is_infinite(),
+            _ => false,
+        }
+    }
+
+    fn is_connectible (&self) -> bool {
+        match &self.kind {
+            VertexKind::Camera => true,
+            VertexKind::Light(light) => !light.is_infinite(),
+            VertexKind::Surface(rec, _) => !rec.material.is_specular(),
+        }
+    }
+
+    // BSDF for scattering towards `next`, without the cosine.
+    fn f (&self, next: &Vertex) -> Color {
+        if let VertexKind::Surface(rec, r_in) = &self.kind {
+            let wi = unit_vector(&(next.p - self.p));
+            let cosine = dot(&rec.shading_normal, &wi).abs();
+            if cosine > 0.0 {
+                return rec.material.eval(r_in, rec, &wi) / cosine;
+            }
+        }
+        Color::default()
+    }
+
+    // Turns a solid angle density at this vertex into an area density at `next`.
+    fn convert_density (&self, pdf: f64, next: &Vertex) -> f64 {
+        if next.is_infinite_light() {
+            return pdf;
+        }
+        let w = next.p - self.p;
+        let distance_squared = w.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let mut pdf = pdf / distance_squared;
+        if next.is_on_surface() {
+            pdf *= dot(&next.normal, &(w / distance_squared.sqrt())).abs();
+        }
+        pdf
+    }
+}
+
+fn remap0 (pdf: f64) -> f64 {
+    if pdf != 0.0 { pdf } else { 1.0 }
+}
+
+fn is_black (c: &Color) -> bool {
+    c.x <= 0.0 && c.y <= 0.0 && c.z <= 0.0
+}
+
+fn random_walk (
+    scene: &Scene,
+    mut ray: Ray,
+    mut beta: Color,
+    pdf: f64,
+    max_depth: usize,
+    path: &mut Vec<Vertex>,
+    escaped: &mut Color,
+) {
+    if max_depth == 0 {
+        return;
+    }
+
+    let mut pdf_fwd = pdf;
+    let mut bounces = 0;
+    loop {
+        let rec = match scene.world.hit(&ray, 0.001, f64::INFINITY) {
+            Some(rec) => rec,
+            None => {
+                *escaped += beta * scene.background.radiance(&ray.direction);
+                break;
+            }
+        };
+
+        let mut vertex = Vertex {
+            kind: VertexKind::Surface(Box::new(rec.clone()), ray),
+            p: rec.p,
+            normal: rec.normal,
+            beta,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            delta: false,
+        };
+        vertex.pdf_fwd = path[path.len() - 1].convert_density(pdf_fwd, &vertex);
+        path.push(vertex);
+
+        bounces += 1;
+        if bounces >= max_depth {
+            break;
+        }
+
+        let (attenuation, scattered, specular) = match rec.material.scatter_lobe(&ray, &rec) {
+            Some(result) => result,
+            None => break,
+        };
+        let direction = unit_vector(&scattered.direction);
+
+        let pdf_rev;
+        if specular {
+            pdf_fwd = 0.0;
+            pdf_rev = 0.0;
+            let current = path.len() - 1;
+            path[current].delta = true;
+        } else {
+            pdf_fwd = rec.material.pdf(&ray, &rec, &direction);
+            pdf_rev = rec.material.pdf(&ray, &rec, &-unit_vector(&ray.direction));
+            if pdf_fwd == 0.0 {
+                break;
+            }
+        }
+
+        beta *= attenuation;
+        if is_black(&beta) {
+            break;
+        }
+
+        let current = path.len() - 1;
+        path[current - 1].pdf_rev = path[current].convert_density(pdf_rev, &path[current - 1]);
+        ray = Ray::new(&rec.p, &direction).with_time(ray.time);
+    }
+}
+
+impl BdptIntegrator {
+    fn camera_subpath (&self, r: &Ray, scene: &Scene, escaped: &mut Color) -> Vec<Vertex> {
+        let direction = unit_vector(&r.direction);
+        let beta = Color::new(1.0, 1.0, 1.0);
+        let mut path = vec![Vertex::endpoint(VertexKind::Camera, r.origin, beta)];
+        let pdf_dir = self.camera.pdf_we(&r.origin, &direction);
+        random_walk(scene, Ray::new(&r.origin, &direction).with_time(r.time), beta, pdf_dir, self.max_depth + 1, &mut path, escaped);
+        path
+    }
+
+    // Leaves the light at `time`, the camera path's.
+    fn light_subpath (&self, scene: &Scene, time: f64) -> Vec<Vertex> {
+        let mut path = Vec::new();
+        let lights = &scene.lights.lights;
+        if lights.is_empty() {
+            return path;
+        }
+
+        let light_pdf = 1.0 / lights.len() as f64;
+        let index = ((random_f64_01() * lights.len() as f64) as usize).min(lights.len() - 1);
+        let light = &lights[index];
+
+        let (center, radius) = scene.bounds();
+        let emission = match light.sample_le(&center, radius) {
+            Some(emission) => emission,
+            None => return path,
+        };
+        if emission.pdf_pos == 0.0 || emission.pdf_dir == 0.0 || is_black(&emission.intensity) {
+            return path;
+        }
+
+        let mut vertex = Vertex::endpoint(VertexKind::Light(light.clone()), emission.ray.origin, emission.intensity);
+        vertex.pdf_fwd = emission.pdf_pos * light_pdf;
+        path.push(vertex);
+
+        let beta = emission.intensity / (light_pdf * emission.pdf_pos * emission.pdf_dir);
+        // light escaping the scene is never seen, so it isn't collected
+        let mut escaped = Color::default();
+        random_walk(scene, emission.ray.with_time(time), beta, emission.pdf_dir, self.max_depth, &mut path, &mut escaped);
+
+        // Directional lights pick a position on a disk rather than a direction.
+        if light.is_infinite() {
+            if path.len() > 1 {
+                path[1].pdf_fwd = emission.pdf_pos * dot(&emission.ray.direction, &path[1].normal).abs();
+            }
+            path[0].pdf_fwd = 0.0;
+        }
+
+        path
+    }
+
+    // Area density of a light emitting towards `next`.
+    fn pdf_light (&self, scene: &Scene, light: &Arc<dyn Light>, from: &Point3, next: &Vertex) -> f64 {
+        let (direction, mut pdf) = if light.is_infinite() {
+            let (_, radius) = scene.bounds();
+            let direction = match light.sample_li(&next.p) {
+                Some(sample) => -sample.wi,
+                None => return 0.0,
+            };
+            (direction, 1.0 / (PI * radius * radius))
+        } else {
+            let w = next.p - *from;
+            let distance_squared = w.length_squared();
+            if distance_squared == 0.0 {
+                return 0.0;
+            }
+            let direction = w / distance_squared.sqrt();
+            (direction, light.pdf_le(&direction) / distance_squared)
+        };
+        if next.is_on_surface() {
+            pdf *= dot(&next.normal, &direction).abs();
+        }
+        pdf
+    }
+
+    // Area density of `v` sampling `next`, having arrived from `prev`.
+    fn pdf (&self, scene: &Scene, v: &Vertex, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+        let wn = next.p - v.p;
+        if wn.length_squared() == 0.0 {
+            return 0.0;
+        }
+        let wn = unit_vector(&wn);
+
+        let pdf = match &v.kind {
+            VertexKind::Light(light) => return self.pdf_light(scene, light, &v.p, next),
+            VertexKind::Camera => self.camera.pdf_we(&v.p, &wn),
+            VertexKind::Surface(rec, r_in) => {
+                let incoming = match prev {
+                    Some(prev) => Ray::new(&prev.p, &(v.p - prev.p)),
+                    None => *r_in,
+                };
+                rec.material.pdf(&incoming, rec, &wn)
+            }
+        };
+        v.convert_density(pdf, next)
+    }
+
+    fn mis_weight (
+        &self,
+        scene: &Scene,
+        camera_path: &[Vertex],
+        light_path: &[Vertex],
+        sampled: Option<Vertex>,
+        s: usize,
+        t: usize,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+
+        let mut camera: Vec<Vertex> = camera_path[..t].to_vec();
+        let mut light: Vec<Vertex> = light_path[..s.min(light_path.len())].to_vec();
+        if let Some(sampled) = sampled {
+            if s == 1 {
+                light = vec![sampled];
+            } else if t == 1 {
+                camera[0] = sampled;
+            }
+        }
+
+        // Update the endpoint densities for this particular connection.
+        camera[t - 1].delta = false;
+        if s > 0 {
+            light[s - 1].delta = false;
+        }
+
+        camera[t - 1].pdf_rev = if s > 0 {
+            let prev = if s > 1 { Some(&light[s - 2]) } else { None };
+            self.pdf(scene, &light[s - 1], prev, &camera[t - 1])
+        } else {
+            0.0
+        };
+        if t > 1 {
+            camera[t - 2].pdf_rev = if s > 0 {
+                self.pdf(scene, &camera[t - 1], Some(&light[s - 1]), &camera[t - 2])
+            } else {
+                0.0
+            };
+        }
+        if s > 0 {
+            let prev = if t > 1 { Some(&camera[t - 2]) } else { None };
+            light[s - 1].pdf_rev = self.pdf(scene, &camera[t - 1], prev, &light[s - 1]);
+        }
+        if s > 1 {
+            light[s - 2].pdf_rev = self.pdf(scene, &light[s - 1], Some(&camera[t - 1]), &light[s - 2]);
+        }
+
+        let mut sum_ri = 0.0;
+
+        let mut ri = 1.0;
+        for i in (1..t).rev() {
+            ri *= remap0(camera[i].pdf_rev) / remap0(camera[i].pdf_fwd);
+            if !camera[i].delta && !camera[i - 1].delta {
+                sum_ri += ri;
+            }
+        }
+
+        let mut ri = 1.0;
+        for i in (0..s).rev() {
+            ri *= remap0(light[i].pdf_rev) / remap0(light[i].pdf_fwd);
+            let delta_light_vertex = if i > 0 { light[i - 1].delta } else { true };
+            if !light[i].delta && !delta_light_vertex {
+                sum_ri += ri;
+            }
+        }
+
+        1.0 / (1.0 + sum_ri)
+    }
+
+    // Joins the first s light and t camera vertices. Returns the weighted contribution
+    // and, for t = 1, the film position it belongs to.
+    fn connect (
+        &self,
+        scene: &Scene,
+        camera_path: &[Vertex],
+        light_path: &[Vertex],
+        s: usize,
+        t: usize,
+        time: f64,
+    ) -> Option<(Color, Option<(f64, f64)>)> {
+        let mut raster = None;
+        let contribution;
+        let mut sampled = None;
+
+        if t == 1 {
+            let qs = &light_path[s - 1];
+            if !qs.is_connectible() {
+                return None;
+            }
+            let sample = self.camera.sample_wi(&qs.p)?;
+            if sample.pdf <= 0.0 || sample.importance <= 0.0 {
+                return None;
+            }
+            let beta = sample.importance / sample.pdf;
+            let vertex = Vertex::endpoint(VertexKind::Camera, sample.lens_point, Color::new(beta, beta, beta));
+            let mut l = qs.beta * qs.f(&vertex) * vertex.beta;
+            if qs.is_on_surface() {
+                l *= dot(&sample.wi, &qs.normal).abs();
+            }
+            if is_black(&l) || !scene.unoccluded(&qs.p, &sample.lens_point, time) {
+                return None;
+            }
+            raster = Some((sample.s, sample.t));
+            contribution = l;
+            sampled = Some(vertex);
+        } else if s == 1 {
+            let pt = &camera_path[t - 1];
+            if !pt.is_connectible() || scene.lights.lights.is_empty() {
+                return None;
+            }
+            let lights = &scene.lights.lights;
+            let light_pdf = 1.0 / lights.len() as f64;
+            let index = ((random_f64_01() * lights.len() as f64) as usize).min(lights.len() - 1);
+            let light = &lights[index];
+
+            let sample = light.sample_li(&pt.p)?;
+            let position = if light.is_infinite() {
+                let (_, radius) = scene.bounds();
+                pt.p + sample.wi * (2.0 * radius)
+            } else {
+                pt.p + sample.wi * sample.distance
+            };
+            // a delta light can never be found by a camera path, so pdf_fwd stays zero
+            let vertex = Vertex::endpoint(VertexKind::Light(light.clone()), position, sample.radiance / light_pdf);
+            let mut l = pt.beta * pt.f(&vertex) * vertex.beta;
+            if pt.is_on_surface() {
+                l *= dot(&sample.wi, &pt.normal).abs();
+            }
+            if is_black(&l) {
+                return None;
+            }
+            if !scene.unoccluded(&pt.p, &position, time) {
+                return None;
+            }
+            contribution = l;
+            sampled = Some(vertex);
+        } else {
+            let qs = &light_path[s - 1];
+            let pt = &camera_path[t - 1];
+            if !qs.is_connectible() || !pt.is_connectible() {
+                return None;
+            }
+            let l = qs.beta * qs.f(pt) * pt.f(qs) * pt.beta;
+            if is_black(&l) {
+                return None;
+            }
+            let d = qs.p - pt.p;
+            let distance_squared = d.length_squared();
+            let d = d / distance_squared.sqrt();
+            let g = dot(&qs.normal, &d).abs() * dot(&pt.normal, &d).abs() / distance_squared;
+            if g == 0.0 || !scene.unoccluded(&pt.p, &qs.p, time) {
+                return None;
+            }
+            contribution = l * g;
+        }
+
+        let weight = self.mis_weight(scene, camera_path, light_path, sampled, s, t);
+        Some((contribution * weight, raster))
+    }
+}
+
+impl Integrator for BdptIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        let mut splats = Vec::new();
+        self.li_splat(r, scene, &mut splats)
+    }
+
+    fn li_splat (&self, r: &Ray, scene: &Scene, splats: &mut Vec<Splat>) -> Color {
+        let mut radiance = Color::default();
+        let camera_path = self.camera_subpath(r, scene, &mut radiance);
+        let light_path = self.light_subpath(scene, r.time);
+
+        for t in 1..=camera_path.len() {
+            for s in 1..=light_path.len().max(1) {
+                if s == 1 && t == 1 {
+                    continue;
+                }
+                if s + t < 2 || s + t - 2 > self.max_depth || (s > 1 && s > light_path.len()) {
+                    continue;
+                }
+                if let Some((contribution, raster)) = self.connect(scene, &camera_path, &light_path, s, t, r.time) {
+                    match raster {
+                        Some((u, v)) => splats.push(Splat { s: u, t: v, color: contribution }),
+                        None => radiance += contribution,
+                    }
+                }
+            }
+        }
+
+        radiance
+    }
+}
@@ -0,0 +1,79 @@
+use crate::common::ray::Ray;
+use crate::common::stats;
+use crate::common::vec3::Color;
+use crate::integrator::aov::id_color;
+use crate::integrator::Integrator;
+use crate::one_week::clamp;
+use crate::one_week::scene::Scene;
+
+pub struct NormalIntegrator;
+
+impl Integrator for NormalIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => (rec.shading_normal + Color::new(1.0, 1.0, 1.0)) * 0.5,
+            None => Color::default(),
+        }
+    }
+}
+
+// Distance to the first hit, black at the camera and white at `max_distance`.
+pub struct DepthIntegrator {
+    pub max_distance: f64,
+}
+
+impl Integrator for DepthIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        let depth = match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => clamp(rec.t * r.direction.length() / self.max_distance, 0.0, 1.0),
+            None => 1.0,
+        };
+        Color::new(depth, depth, depth)
+    }
+}
+
+pub struct UvIntegrator;
+
+impl Integrator for UvIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => Color::new(rec.u, rec.v, 0.0),
+            None => Color::default(),
+        }
+    }
+}
+
+// Every material instance gets a stable pseudo-random color for this run.
+pub struct MaterialIdIntegrator;
+
+impl Integrator for MaterialIdIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => match scene.material_id(&rec.material) {
+                0 => Color::default(),
+                id => id_color(id),
+            },
+            None => Color::default(),
+        }
+    }
+}
+
+// Heat map of BVH nodes visited by the camera ray, blue through red up to `max_visits`.
+pub struct BvhCostIntegrator {
+    pub max_visits: u64,
+}
+
+impl Integrator for BvhCostIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        let before = stats::current().node_visits;
+        scene.world.hit(r, 0.001, f64::INFINITY);
+        let visits = stats::current().node_visits - before;
+        let cost = clamp(visits as f64 / self.max_visits as f64, 0.0, 1.0);
+
+        if cost < 0.5 {
+            Color::new(0.0, cost * 2.0, 1.0 - cost * 2.0)
+        } else {
+            Color::new(cost * 2.0 - 1.0, 2.0 - cost * 2.0, 0.0)
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, ThreadId};
+
+use crate::common::camera::Camera;
+use crate::common::ray::Ray;
+use crate::common::sampler::{self, PrimarySampler};
+use crate::common::vec3::Color;
+use crate::integrator::{Integrator, Splat};
+use crate::integrator::path::PathIntegrator;
+use crate::one_week::random_f64_01;
+use crate::one_week::scene::Scene;
+
+// Primary sample space Metropolis light transport (Kelemen et al.) over the path
+// tracer. A path is a function of the random numbers `Camera::get_ray` and
+// `Material::scatter` consume, so mutating those numbers explores path space
+// without knowing anything about it. Every camera sample advances the calling
+// thread's Markov chain once and splats wherever the chain is; the ray it is handed
+// is ignored. Brightness is restored by `b`, the mean luminance of independent
+// bootstrap paths, which are traced before the render starts.
+pub struct MltIntegrator {
+    camera: Arc<Camera>,
+    path: PathIntegrator,
+    sigma: f64,
+    large_step_probability: f64,
+    bootstrap_samples: usize,
+    seed: u64,
+    bootstrap: RwLock<Option<Bootstrap>>,
+    // one chain per rendering thread, each started on its first sample
+    chains: Mutex<HashMap<ThreadId, Chain>>,
+}
+
+struct Bootstrap {
+    b: f64,
+    // seeds and running luminance totals of the paths, chains start from one of them
+    paths: Vec<(u64, f64)>,
+}
+
+struct Chain {
+    sampler: PrimarySampler,
+    s: f64,
+    t: f64,
+    radiance: Color,
+}
+
+fn luminance (c: &Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+impl MltIntegrator {
+    pub fn new (camera: Arc<Camera>, path: PathIntegrator, bootstrap_samples: usize, seed: u64) -> Self {
+        Self {
+            camera,
+            path,
+            sigma: 0.01,
+            large_step_probability: 0.3,
+            bootstrap_samples,
+            seed,
+            bootstrap: RwLock::new(None),
+            chains: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn trace_bootstrap (&self, scene: &Scene) -> Bootstrap {
+        let mut paths = Vec::with_capacity(self.bootstrap_samples);
+        let mut total = 0.0;
+        for i in 0..self.bootstrap_samples as u64 {
+            let seed = sampler::mix(self.seed, i);
+            let (_, (_, _, radiance)) = self.evaluate(self.primary(seed), scene);
+            total += luminance(&radiance);
+            paths.push((seed, total));
+        }
+        Bootstrap { b: total / self.bootstrap_samples.max(1) as f64, paths }
+    }
+
+    fn primary (&self, seed: u64) -> PrimarySampler {
+        PrimarySampler::new(seed, self.sigma, self.large_step_probability)
+    }
+
+    // Runs one iteration of `sampler`: a film position, then a path through it.
+    fn evaluate (&self, mut sampler: PrimarySampler, scene: &Scene) -> (PrimarySampler, (f64, f64, Color)) {
+        sampler.start_iteration();
+        sampler::with_primary(sampler, || {
+            let s = random_f64_01();
+            let t = random_f64_01();
+            let radiance = match self.camera.get_ray(s, t) {
+                Some((weight, r)) => self.path.li(&r, scene) * weight,
+                None => Color::default(),
+            };
+            (s, t, radiance)
+        })
+    }
+
+    // Picks a bootstrap path in proportion to its luminance and replays it.
+    fn start_chain (&self, bootstrap: &Bootstrap, scene: &Scene) -> Option<Chain> {
+        let paths = &bootstrap.paths;
+        let total = paths.last()?.1;
+        if total <= 0.0 {
+            return None;
+        }
+        let target = random_f64_01() * total;
+        let index = paths.partition_point(|&(_, cdf)| cdf <= target).min(paths.len() - 1);
+
+        let (mut sampler, (s, t, radiance)) = self.evaluate(self.primary(paths[index].0), scene);
+        sampler.accept();
+        Some(Chain { sampler, s, t, radiance })
+    }
+
+    fn mutate (&self, chain: Chain, b: f64, scene: &Scene, splats: &mut Vec<Splat>) -> Chain {
+        let current = luminance(&chain.radiance);
+        let (mut sampler, (s, t, radiance)) = self.evaluate(chain.sampler, scene);
+        let proposed = luminance(&radiance);
+        let accept = if current > 0.0 { (proposed / current).min(1.0) } else { 1.0 };
+
+        // Both states are splatted with their expected weights, which keeps rarely
+        // accepted proposals from being wasted.
+        if accept > 0.0 {
+            splats.push(Splat { s, t, color: radiance * (b * accept / proposed) });
+        }
+        if accept < 1.0 && current > 0.0 {
+            splats.push(Splat { s: chain.s, t: chain.t, color: chain.radiance * (b * (1.0 - accept) / current) });
+        }
+
+        if sampler.uniform() < accept {
+            sampler.accept();
+            Chain { sampler, s, t, radiance }
+        } else {
+            sampler.reject();
+            Chain { sampler, ..chain }
+        }
+    }
+}
+
+impl Integrator for MltIntegrator {
+    // on the calling thread, where the renderer counts its rays with the rest
+    fn begin_pass (&self, scene: &Scene, _pass: usize) {
+        let mut bootstrap = self.bootstrap.write().unwrap();
+        if bootstrap.is_none() {
+            *bootstrap = Some(self.trace_bootstrap(scene));
+        }
+    }
+
+    fn li (&self, _r: &Ray, _scene: &Scene) -> Color {
+        Color::default()
+    }
+
+    fn li_splat (&self, _r: &Ray, scene: &Scene, splats: &mut Vec<Splat>) -> Color {
+        self.splat_only(scene, splats);
+        Color::default()
+    }
+
+    fn splat_only (&self, scene: &Scene, splats: &mut Vec<Splat>) {
+        // taken out while it mutates, so other threads aren't held up
+        let bootstrap = self.bootstrap.read().unwrap();
+        let bootstrap = bootstrap.as_ref().expect("begin_pass traces the bootstrap paths");
+        let thread = thread::current().id();
+        let chain = self.chains.lock().unwrap().remove(&thread);
+        if let Some(chain) = chain.or_else(|| self.start_chain(bootstrap, scene)) {
+            let chain = self.mutate(chain, bootstrap.b, scene, splats);
+            self.chains.lock().unwrap().insert(thread, chain);
+        }
+    }
+}
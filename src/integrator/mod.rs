@@ -0,0 +1,61 @@
+use crate::common::ray::Ray;
+use crate::common::vec3::Color;
+use crate::integrator::aov::Aovs;
+use crate::one_week::scene::Scene;
+
+pub mod path;
+pub mod ao;
+pub mod whitted;
+pub mod debug;
+pub mod bdpt;
+pub mod photon;
+pub(crate) mod photon_map;
+pub mod mlt;
+pub mod spectral;
+pub mod aov;
+
+// Radiance landing at film position (s, t) instead of the pixel being rendered.
+pub struct Splat {
+    pub s: f64,
+    pub t: f64,
+    pub color: Color,
+}
+
+pub trait Integrator: Send + Sync {
+    // Radiance arriving at the camera along `r`.
+    fn li (&self, r: &Ray, scene: &Scene) -> Color;
+
+    // Integrators that trace from the lights also deposit radiance anywhere on the film.
+    fn li_splat (&self, r: &Ray, scene: &Scene, _splats: &mut Vec<Splat>) -> Color {
+        self.li(r, scene)
+    }
+
+    // Stands in for `li_splat` on camera samples whose film position sees nothing, so
+    // integrators that spread their work over the whole film still do their share.
+    fn splat_only (&self, _scene: &Scene, _splats: &mut Vec<Splat>) {}
+
+    // Integrators that refine the whole image a camera sample per pixel at a time are
+    // rendered in as many passes as there are samples per pixel.
+    fn progressive (&self) -> bool {
+        false
+    }
+
+    // Called on the rendering thread before the camera samples of each pass, counting
+    // from 0, for work that has to be done first. Renders that aren't progressive are
+    // a single pass.
+    fn begin_pass (&self, _scene: &Scene, _pass: usize) {}
+
+    // `li_splat` that also fills in what the ray saw first. By default the ray is cast
+    // once more for that, and the lighting isn't split: only emission seen straight
+    // away is known, direct and indirect light stay black.
+    fn li_aovs (&self, r: &Ray, scene: &Scene, splats: &mut Vec<Splat>, aovs: &mut Aovs) -> Color {
+        *aovs = match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => Aovs {
+                albedo: rec.material.scatter(r, &rec).map(|(attenuation, _)| attenuation).unwrap_or_default(),
+                ..Aovs::at_hit(r, &rec, scene)
+            },
+            None => Aovs::at_miss(r, scene),
+        };
+        self.li_splat(r, scene, splats)
+    }
+}
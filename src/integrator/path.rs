@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use crate::common::ray::Ray;
+use crate::common::vec3::{Color, Vec3};
+use crate::common::vec3_opts::unit_vector;
+use crate::integrator::aov::Aovs;
+use crate::integrator::{Integrator, Splat};
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::material::Material;
+use crate::one_week::medium::{MediumEvent, MediumStack};
+use crate::one_week::random_f64_01;
+use crate::one_week::scene::Scene;
+
+// Unidirectional path tracer. Without `next_event` only the background is ever
+// found, delta lights need the shadow rays of next-event estimation. Each path keeps
+// the stack of media it has refracted into, is absorbed along the way and random
+// walks through the ones that scatter.
+pub struct PathIntegrator {
+    pub max_depth: i32,
+    pub rr_min_depth: i32,
+    pub next_event: bool,
+}
+
+impl Integrator for PathIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        self.trace(r, scene, None)
+    }
+
+    fn li_aovs (&self, r: &Ray, scene: &Scene, _splats: &mut Vec<Splat>, aovs: &mut Aovs) -> Color {
+        self.trace(r, scene, Some(aovs))
+    }
+}
+
+impl PathIntegrator {
+    // Paths are ended by Russian roulette once they are `rr_min_depth` bounces long,
+    // survivors are reweighted so the estimate stays unbiased. `max_depth` is only a
+    // safety net against paths that never lose energy. With `aovs` the first hit is
+    // recorded and the light sorted by the bounce it was found at.
+    fn trace (&self, r: &Ray, scene: &Scene, mut aovs: Option<&mut Aovs>) -> Color {
+        let mut radiance = Color::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *r;
+        let mut media = MediumStack::default();
+
+        for depth in 0..self.max_depth {
+            let mut rec = match scene.world.hit(&ray, 0.001, f64::INFINITY) {
+                Some(rec) => rec,
+                None => {
+                    let found = throughput * scene.background.radiance(&ray.direction);
+                    radiance += found;
+                    match aovs {
+                        Some(aovs) if depth == 0 => *aovs = Aovs::at_miss(&ray, scene),
+                        Some(aovs) => aovs.add_light(depth, found),
+                        None => {}
+                    }
+                    break;
+                }
+            };
+
+            match media.sample(rec.t * ray.direction.length()) {
+                MediumEvent::Scatter { distance, weight, phase } => {
+                    throughput *= weight;
+                    rec = medium_interaction(&ray, distance, phase);
+                }
+                MediumEvent::Pass { weight } => throughput *= weight,
+            }
+            if depth == 0 {
+                if let Some(aovs) = aovs.as_deref_mut() {
+                    *aovs = Aovs::at_hit(&ray, &rec, scene);
+                }
+            }
+
+            if self.next_event {
+                let found = throughput * scene.sample_lights(&ray, &rec);
+                radiance += found;
+                if let Some(aovs) = aovs.as_deref_mut() {
+                    aovs.add_light(depth + 1, found);
+                }
+            }
+
+            match rec.material.scatter_in(&ray, &rec, &mut media) {
+                Some((attenuation, scattered)) => {
+                    if let (Some(aovs), 0) = (aovs.as_deref_mut(), depth) {
+                        aovs.albedo = attenuation;
+                    }
+                    throughput *= attenuation;
+                    ray = scattered;
+                }
+                None => break,
+            }
+
+            if depth + 1 >= self.rr_min_depth {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).min(0.95);
+                if random_f64_01() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+        }
+
+        radiance
+    }
+}
+
+// Scattering point `distance` along `r` inside a medium, shaded by its phase function.
+pub fn medium_interaction (r: &Ray, distance: f64, phase: Arc<dyn Material>) -> HitRecord {
+    let direction = unit_vector(&r.direction);
+    let t = distance / r.direction.length();
+    HitRecord {
+        p: r.at(t),
+        normal: -direction,
+        shading_normal: -direction,
+        dpdu: Vec3::default(),
+        dpdv: Vec3::default(),
+        material: phase,
+        t,
+        u: 0.0,
+        v: 0.0,
+        front_face: true,
+        object_id: 0,
+    }
+}
@@ -0,0 +1,184 @@
+use std::f64::consts::PI;
+use std::sync::RwLock;
+
+use crate::common::ray::Ray;
+use crate::common::vec3::Color;
+use crate::common::vec3_opts::{dot, unit_vector};
+use crate::integrator::Integrator;
+use crate::integrator::path::PathIntegrator;
+use crate::integrator::photon_map::{Photon, PhotonMap};
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::random_f64_01;
+use crate::one_week::scene::Scene;
+
+// Photon mapping with separate caustic (light, specular, diffuse) and global maps.
+// Direct light comes from shadow rays and the background from a path that ignores
+// the lights, so each light path is counted once. This is Knaus and Zwicker's
+// progressive photon mapping: the image is rendered a camera sample per pixel at a
+// time, each pass with fresh photons and a smaller radius than the last, and every
+// pixel averages the estimates of its passes. Only the current pass's maps are kept,
+// and the bias goes away as the samples per pixel grow.
+pub struct PhotonIntegrator {
+    photons_per_pass: usize,
+    initial_radius: f64,
+    max_depth: i32,
+    rr_min_depth: i32,
+    pass: RwLock<Option<PhotonPass>>,
+}
+
+struct PhotonPass {
+    caustic: PhotonMap,
+    global: PhotonMap,
+    radius: f64,
+}
+
+// Radius shrink factor of progressive photon mapping, between 0 and 1.
+const ALPHA: f64 = 2.0 / 3.0;
+
+impl PhotonIntegrator {
+    pub fn new (photons_per_pass: usize, initial_radius: f64, max_depth: i32, rr_min_depth: i32) -> Self {
+        Self { photons_per_pass, initial_radius, max_depth, rr_min_depth, pass: RwLock::new(None) }
+    }
+
+    // Search radius of pass `pass`, its square shrinking by (i + alpha) / (i + 1) in
+    // the i-th pass counting from 1.
+    fn radius (&self, pass: usize) -> f64 {
+        let mut radius_squared = self.initial_radius * self.initial_radius;
+        for i in 1..=pass {
+            radius_squared *= (i as f64 + ALPHA) / (i as f64 + 1.0);
+        }
+        radius_squared.sqrt()
+    }
+}
+
+fn trace_photons (scene: &Scene, count: usize, max_depth: i32, rr_min_depth: i32) -> (PhotonMap, PhotonMap) {
+    let mut caustic = Vec::new();
+    let mut global = Vec::new();
+    let lights = &scene.lights.lights;
+    if lights.is_empty() || count == 0 {
+        return (PhotonMap::build(caustic), PhotonMap::build(global));
+    }
+
+    let (center, radius) = scene.bounds();
+    let light_pdf = 1.0 / lights.len() as f64;
+
+    for _ in 0..count {
+        let index = ((random_f64_01() * lights.len() as f64) as usize).min(lights.len() - 1);
+        let emission = match lights[index].sample_le(&center, radius) {
+            Some(emission) => emission,
+            None => continue,
+        };
+        if emission.pdf_pos == 0.0 || emission.pdf_dir == 0.0 {
+            continue;
+        }
+
+        let mut power = emission.intensity / (light_pdf * emission.pdf_pos * emission.pdf_dir * count as f64);
+        let mut ray = emission.ray;
+        let mut specular_only = true;
+
+        for depth in 0..max_depth {
+            let rec = match scene.world.hit(&ray, 0.001, f64::INFINITY) {
+                Some(rec) => rec,
+                None => break,
+            };
+
+            // Direct hits are left to shadow rays.
+            if !rec.material.is_specular() && depth > 0 {
+                let photon = Photon {
+                    position: rec.p,
+                    direction: unit_vector(&ray.direction),
+                    power,
+                };
+                if specular_only {
+                    caustic.push(photon);
+                } else {
+                    global.push(photon);
+                }
+            }
+
+            let (attenuation, scattered, specular) = match rec.material.scatter_lobe(&ray, &rec) {
+                Some(result) => result,
+                None => break,
+            };
+            specular_only = specular_only && specular;
+            power *= attenuation;
+            ray = scattered;
+
+            if depth + 1 >= rr_min_depth {
+                let survival = attenuation.x.max(attenuation.y).max(attenuation.z).min(0.95);
+                if random_f64_01() >= survival {
+                    break;
+                }
+                power /= survival;
+            }
+        }
+    }
+
+    (PhotonMap::build(caustic), PhotonMap::build(global))
+}
+
+// Reflected radiance from the photons around `rec`, with a constant kernel.
+fn estimate (map: &PhotonMap, r_in: &Ray, rec: &HitRecord, radius: f64) -> Color {
+    let mut flux = Color::default();
+    map.query(&rec.p, radius, &mut |photon: &Photon| {
+        let wi = -photon.direction;
+        let cosine = dot(&rec.shading_normal, &wi);
+        if cosine > 0.0 {
+            flux += rec.material.eval(r_in, rec, &wi) / cosine * photon.power;
+        }
+    });
+    flux / (PI * radius * radius)
+}
+
+impl Integrator for PhotonIntegrator {
+    fn progressive (&self) -> bool {
+        true
+    }
+
+    fn begin_pass (&self, scene: &Scene, pass: usize) {
+        // the last pass's maps go first, so there is only ever one set
+        let mut current = self.pass.write().unwrap();
+        *current = None;
+        let (caustic, global) = trace_photons(scene, self.photons_per_pass, self.max_depth, self.rr_min_depth);
+        *current = Some(PhotonPass { caustic, global, radius: self.radius(pass) });
+    }
+
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        let current = self.pass.read().unwrap();
+        let pass = current.as_ref().expect("begin_pass traces the photons");
+        let sky = PathIntegrator {
+            max_depth: self.max_depth,
+            rr_min_depth: self.rr_min_depth,
+            next_event: false,
+        };
+
+        let mut radiance = Color::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *r;
+        for _ in 0..self.max_depth {
+            let rec = match scene.world.hit(&ray, 0.001, f64::INFINITY) {
+                Some(rec) => rec,
+                None => return radiance + throughput * scene.background.radiance(&ray.direction),
+            };
+
+            if !rec.material.is_specular() {
+                radiance += throughput
+                    * (scene.sample_lights(&ray, &rec)
+                        + estimate(&pass.caustic, &ray, &rec, pass.radius)
+                        + estimate(&pass.global, &ray, &rec, pass.radius));
+            }
+
+            // the photons stand in for everything but the sky behind a diffuse bounce
+            match rec.material.scatter_lobe(&ray, &rec) {
+                Some((attenuation, next, false)) => return radiance + throughput * attenuation * sky.li(&next, scene),
+                Some((attenuation, next, true)) => {
+                    throughput *= attenuation;
+                    ray = next;
+                }
+                None => break,
+            }
+        }
+
+        radiance
+    }
+}
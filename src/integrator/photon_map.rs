@@ -0,0 +1,88 @@
+use std::cmp::Ordering;
+
+use crate::common::vec3::{Color, Point3, Vec3};
+
+pub struct Photon {
+    pub position: Point3,
+    // direction the photon was travelling in
+    pub direction: Vec3,
+    pub power: Color,
+}
+
+// Balanced k-d tree stored in place: the median of every range is its root.
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    axes: Vec<usize>,
+}
+
+impl PhotonMap {
+    pub fn build (mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        let len = photons.len();
+        Self::build_range(&mut photons, &mut axes, 0, len);
+        Self { photons, axes }
+    }
+
+    fn build_range (photons: &mut [Photon], axes: &mut [usize], lo: usize, hi: usize) {
+        if hi - lo <= 1 {
+            return;
+        }
+
+        let mut min = photons[lo].position;
+        let mut max = photons[lo].position;
+        for photon in photons[lo..hi].iter() {
+            let p = photon.position;
+            min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        let extent = max - min;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        };
+
+        let mid = (lo + hi) / 2;
+        photons[lo..hi].select_nth_unstable_by(mid - lo, |a, b| {
+            a.position[axis].partial_cmp(&b.position[axis]).unwrap_or(Ordering::Equal)
+        });
+        axes[mid] = axis;
+
+        Self::build_range(photons, axes, lo, mid);
+        Self::build_range(photons, axes, mid + 1, hi);
+    }
+
+    // Calls `f` for every photon within `radius` of `p`.
+    pub fn query<F: FnMut(&Photon)> (&self, p: &Point3, radius: f64, f: &mut F) {
+        self.query_range(0, self.photons.len(), p, radius * radius, f);
+    }
+
+    fn query_range<F: FnMut(&Photon)> (&self, lo: usize, hi: usize, p: &Point3, radius_squared: f64, f: &mut F) {
+        if lo >= hi {
+            return;
+        }
+
+        let mid = (lo + hi) / 2;
+        let photon = &self.photons[mid];
+        if (photon.position - *p).length_squared() <= radius_squared {
+            f(photon);
+        }
+        if hi - lo == 1 {
+            return;
+        }
+
+        let axis = self.axes[mid];
+        let d = p[axis] - photon.position[axis];
+        let (near, far) = if d < 0.0 {
+            ((lo, mid), (mid + 1, hi))
+        } else {
+            ((mid + 1, hi), (lo, mid))
+        };
+        self.query_range(near.0, near.1, p, radius_squared, f);
+        if d * d <= radius_squared {
+            self.query_range(far.0, far.1, p, radius_squared, f);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::common::ray::Ray;
+use crate::common::spectrum::{Spectrum, Wavelengths};
+use crate::common::vec3::Color;
+use crate::integrator::Integrator;
+use crate::integrator::path::medium_interaction;
+use crate::one_week::medium::{MediumEvent, MediumStack};
+use crate::one_week::random_f64_01;
+use crate::one_week::scene::Scene;
+
+// The path tracer at a handful of wavelengths per path instead of RGB, so dielectrics
+// with a dispersive medium split light into colours. RGB albedos, lights, media and the
+// background are upsampled where the path meets them (direct light after shading in
+// RGB), and the estimate is brought back to linear sRGB through CIE XYZ.
+pub struct SpectralPathIntegrator {
+    pub max_depth: i32,
+    pub rr_min_depth: i32,
+}
+
+impl Integrator for SpectralPathIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        let mut lambdas = Wavelengths::sample(random_f64_01());
+        let mut radiance = Spectrum::constant(0.0);
+        let mut throughput = Spectrum::constant(1.0);
+        let mut ray = *r;
+        let mut media = MediumStack::default();
+
+        for depth in 0..self.max_depth {
+            let mut rec = match scene.world.hit(&ray, 0.001, f64::INFINITY) {
+                Some(rec) => rec,
+                None => {
+                    radiance += throughput * Spectrum::from_rgb(&scene.background.radiance(&ray.direction), &lambdas);
+                    break;
+                }
+            };
+
+            match media.sample(rec.t * ray.direction.length()) {
+                MediumEvent::Scatter { distance, weight, phase } => {
+                    throughput *= Spectrum::from_rgb(&weight, &lambdas);
+                    rec = medium_interaction(&ray, distance, phase);
+                }
+                MediumEvent::Pass { weight } => throughput *= Spectrum::from_rgb(&weight, &lambdas),
+            }
+            radiance += throughput * Spectrum::from_rgb(&scene.sample_lights(&ray, &rec), &lambdas);
+
+            match rec.material.scatter_spectral(&ray, &rec, &mut media, &mut lambdas) {
+                Some((attenuation, scattered)) => {
+                    throughput *= attenuation;
+                    ray = scattered;
+                }
+                None => break,
+            }
+
+            if depth + 1 >= self.rr_min_depth {
+                let survival = throughput.max_value().min(0.95);
+                if random_f64_01() >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+        }
+
+        radiance.to_rgb(&lambdas)
+    }
+}
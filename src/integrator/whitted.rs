@@ -0,0 +1,42 @@
+use crate::common::ray::Ray;
+use crate::common::vec3::Color;
+use crate::integrator::Integrator;
+use crate::one_week::scene::Scene;
+
+// Direct light on diffuse surfaces plus an ambient term from the background,
+// specular surfaces and delta lobes are followed until something diffuse is found.
+pub struct WhittedIntegrator {
+    pub max_depth: i32,
+}
+
+impl Integrator for WhittedIntegrator {
+    fn li (&self, r: &Ray, scene: &Scene) -> Color {
+        let mut radiance = Color::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *r;
+
+        for _ in 0..self.max_depth {
+            let rec = match scene.world.hit(&ray, 0.001, f64::INFINITY) {
+                Some(rec) => rec,
+                None => return radiance + throughput * scene.background.radiance(&ray.direction),
+            };
+
+            if !rec.material.is_specular() {
+                radiance += throughput * scene.sample_lights(&ray, &rec);
+            }
+
+            match rec.material.scatter_lobe(&ray, &rec) {
+                Some((attenuation, _, false)) => {
+                    return radiance + throughput * attenuation * scene.background.radiance(&rec.normal);
+                }
+                Some((attenuation, next, true)) => {
+                    throughput *= attenuation;
+                    ray = next;
+                }
+                None => break,
+            }
+        }
+
+        radiance
+    }
+}
@@ -0,0 +1,26 @@
+// A CPU ray tracer that grew out of the "Ray Tracing in One Weekend" books. Scenes are
+// built from `Hittable` objects with `Material`s, `Light`s and a `Background`, looked
+// at through a `Camera`, and turned into pixels by a `Renderer` with one of the
+// `Integrator`s.
+
+pub mod common;
+pub mod one_week;
+pub mod integrator;
+pub mod renderer;
+pub mod scenes;
+
+pub use common::camera::{Camera, CameraFrame, CameraModel, Perspective};
+pub use common::exr::encode_exr;
+pub use common::framebuffer::Framebuffer;
+pub use common::image::Image;
+pub use common::ray::Ray;
+pub use common::sampler::set_seed;
+pub use common::stats::RayStats;
+pub use common::vec3::{Color, Point3, Vec3};
+pub use integrator::Integrator;
+pub use one_week::hittable::{HitRecord, Hittable};
+pub use one_week::hittable_list::HittableList;
+pub use one_week::light::{Light, LightList};
+pub use one_week::material::Material;
+pub use one_week::scene::{Background, Scene};
+pub use renderer::Renderer;
@@ -1,7 +1,5 @@
-mod common;
-mod one_week;
 mod main_one_week;
 
 fn main() -> std::io::Result<()> {
     main_one_week::entry()
-}
\ No newline at end of file
+}
@@ -1,189 +1,562 @@
-use std::io::prelude::*;
-use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::env;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::f64::INFINITY;
-
-use crate::common::vec3::{Vec3,Color, Point3};
-use crate::common::ray::Ray;
-use crate::common::vec3_opts::unit_vector;
-use crate::common::color::format_color;
-use crate::common::camera::Camera;
-
-use crate::one_week::hittable::{HitRecord, Hittable};
-use crate::one_week::hittable_list::HittableList;
-use crate::one_week::material::{Lambertian, Material, Metal, Dielectric};
-use crate::one_week::{random_f64, random_f64_01};
-use crate::one_week::sphere::Sphere;
-use std::thread::{spawn, sleep};
-use std::collections::HashMap;
-use std::time::Duration;
-
-fn ray_color (r: &Ray, world: &Arc<HittableList>, depth: i32) -> Color {
-    let rec: HitRecord;
-
-    if depth <= 0 {
-        return Color::default();
-    }
+use std::sync::Arc;
 
-    if let Some(rec) = world.hit(r, 0.001, INFINITY) {
-        if let Some((attenuation, scattered)) = rec.material.scatter(r, &rec) {
-            return attenuation * ray_color(&scattered, world, depth - 1);
-        }
-        return Color::default();
-    }
+use ray_tracing_in_one_weekend::common::vec3::{Vec3, Point3};
+use ray_tracing_in_one_weekend::common::aperture::Aperture;
+use ray_tracing_in_one_weekend::common::camera::{Camera, CameraModel, Exposure, Perspective};
+use ray_tracing_in_one_weekend::common::keyframe::{CameraPath, Interpolation, Key, Track};
+use ray_tracing_in_one_weekend::common::projection::{CubeMap, Equirectangular, Fisheye, Orthographic};
+use ray_tracing_in_one_weekend::common::realistic::{Realistic, DOUBLE_GAUSS_50MM};
+use ray_tracing_in_one_weekend::common::stereo::{Ods, Stereo, StereoLayout};
+
+use ray_tracing_in_one_weekend::integrator::Integrator;
+use ray_tracing_in_one_weekend::integrator::ao::AmbientOcclusionIntegrator;
+use ray_tracing_in_one_weekend::integrator::bdpt::BdptIntegrator;
+use ray_tracing_in_one_weekend::integrator::debug::{BvhCostIntegrator, DepthIntegrator, MaterialIdIntegrator, NormalIntegrator, UvIntegrator};
+use ray_tracing_in_one_weekend::integrator::mlt::MltIntegrator;
+use ray_tracing_in_one_weekend::common::denoise::Denoiser;
+use ray_tracing_in_one_weekend::common::image::Image;
+use ray_tracing_in_one_weekend::integrator::aov::Aov;
+use ray_tracing_in_one_weekend::integrator::path::PathIntegrator;
+use ray_tracing_in_one_weekend::integrator::spectral::SpectralPathIntegrator;
+use ray_tracing_in_one_weekend::integrator::photon::PhotonIntegrator;
+use ray_tracing_in_one_weekend::integrator::whitted::WhittedIntegrator;
+use ray_tracing_in_one_weekend::one_week::light::IesProfile;
+use ray_tracing_in_one_weekend::one_week::random_f64_01;
+use ray_tracing_in_one_weekend::one_week::scene::Scene;
+use ray_tracing_in_one_weekend::one_week::texture::ImageTexture;
+use ray_tracing_in_one_weekend::one_week::volume::VoxelGrid;
+use ray_tracing_in_one_weekend::renderer::Renderer;
+use ray_tracing_in_one_weekend::scenes::*;
+use ray_tracing_in_one_weekend::{encode_exr, set_seed, RayStats};
 
-    let unit_direction = unit_vector(&r.direction);
-    let t = unit_direction.y * 0.5 + f64::from(1);
-    Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+struct Options {
+    scene: String,
+    integrator: String,
+    ies: Option<String>,
+    volume: Option<String>,
+    normal_map: Option<String>,
+    camera: String,
+    fov: Option<f64>,
+    stereo: Option<String>,
+    interaxial: f64,
+    convergence: f64,
+    aperture: f64,
+    lens: Option<String>,
+    stop_diameter: Option<f64>,
+    blades: Option<usize>,
+    aperture_mask: Option<String>,
+    shift: (f64, f64),
+    tilt: (f64, f64),
+    f_number: Option<f64>,
+    shutter: Option<f64>,
+    iso: Option<f64>,
+    image_width: i32,
+    samples_per_pixel: i32,
+    max_depth: Option<i32>,
+    rr_min_depth: i32,
+    photons: usize,
+    photon_radius: f64,
+    bootstrap: usize,
+    seed: Option<u64>,
+    camera_path: Option<String>,
+    frames: Option<(i32, i32)>,
+    fps: f64,
+    aovs: Vec<Aov>,
+    denoise: bool,
+    crop: Option<(usize, usize, usize, usize)>,
+    crop_base: Option<String>,
+    stats: Option<String>,
+    output: Option<String>,
 }
 
-fn random_scene () -> HittableList {
-    let mut world = HittableList {
-        objects: Vec::<Arc<dyn Hittable>>::new()
+fn parse_args () -> std::io::Result<Options> {
+    let mut options = Options {
+        scene: String::from("random"),
+        integrator: String::from("path"),
+        ies: None,
+        volume: None,
+        normal_map: None,
+        camera: String::from("perspective"),
+        fov: None,
+        stereo: None,
+        interaxial: 0.3,
+        convergence: 10.0,
+        aperture: 0.1,
+        lens: None,
+        stop_diameter: None,
+        blades: None,
+        aperture_mask: None,
+        shift: (0.0, 0.0),
+        tilt: (0.0, 0.0),
+        f_number: None,
+        shutter: None,
+        iso: None,
+        image_width: 1200,
+        samples_per_pixel: 200,
+        max_depth: None,
+        rr_min_depth: 5,
+        photons: 20_000,
+        photon_radius: 0.1,
+        bootstrap: 100_000,
+        seed: None,
+        camera_path: None,
+        frames: None,
+        fps: 24.0,
+        aovs: Vec::new(),
+        denoise: false,
+        crop: None,
+        crop_base: None,
+        stats: None,
+        output: None,
     };
 
-    // ground material
-    world.add(Arc::new(Sphere::new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        Box::new(Lambertian {
-            albedo: Color::new(0.5, 0.5, 0.5)
-        })
-    )));
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let choose_mat = random_f64_01();
-            let center = Point3::new(
-                f64::from(a) + 0.9 * random_f64_01(),
-                0.2,
-                f64::from(b) + 0.9 * random_f64_01()
-            );
-
-            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                if choose_mat < 0.8 {
-                    let albedo = Color::random_01() * Color::random_01();
-                    world.add(Arc::new(Sphere::new(
-                        center, 0.2, Box::new(Lambertian { albedo })
-                    )));
-                } else if choose_mat < 0.95 {
-                    let albedo = Color::random(0.5, 1.0);
-                    let fuzz = random_f64(0.0, 0.5);
-                    world.add(Arc::new(Sphere::new(
-                        center, 0.2, Box::new(Metal::new(albedo, fuzz))
-                    )));
-                } else {
-                    world.add(Arc::new(Sphere::new(
-                        center, 0.2, Box::new(Dielectric::new(1.5))
-                    )));
-                }
+    let invalid = |msg: String| Error::new(ErrorKind::InvalidInput, msg);
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or_else(|| invalid(format!("missing value for {}", flag)));
+        match flag.as_str() {
+            "--scene" => options.scene = value()?,
+            "--integrator" => options.integrator = value()?,
+            "--ies" => options.ies = Some(value()?),
+            "--volume" => options.volume = Some(value()?),
+            "--normal-map" => options.normal_map = Some(value()?),
+            "--camera" => options.camera = value()?,
+            "--fov" => options.fov = Some(value()?.parse().map_err(|_| invalid(String::from("bad --fov")))?),
+            "--stereo" => options.stereo = Some(value()?),
+            "--interaxial" => options.interaxial = value()?.parse().map_err(|_| invalid(String::from("bad --interaxial")))?,
+            "--convergence" => {
+                options.convergence = value()?
+                    .parse()
+                    .ok()
+                    .filter(|&convergence: &f64| convergence > 0.0)
+                    .ok_or_else(|| invalid(String::from("--convergence must be a positive distance")))?
+            }
+            "--aperture" => options.aperture = value()?.parse().map_err(|_| invalid(String::from("bad --aperture")))?,
+            "--lens" => options.lens = Some(value()?),
+            "--stop" => options.stop_diameter = Some(value()?.parse().map_err(|_| invalid(String::from("bad --stop")))?),
+            "--blades" => options.blades = Some(value()?.parse().map_err(|_| invalid(String::from("bad --blades")))?),
+            "--aperture-mask" => options.aperture_mask = Some(value()?),
+            "--shift-x" => options.shift.0 = value()?.parse().map_err(|_| invalid(String::from("bad --shift-x")))?,
+            "--shift-y" => options.shift.1 = value()?.parse().map_err(|_| invalid(String::from("bad --shift-y")))?,
+            "--tilt" => options.tilt.0 = value()?.parse().map_err(|_| invalid(String::from("bad --tilt")))?,
+            "--swing" => options.tilt.1 = value()?.parse().map_err(|_| invalid(String::from("bad --swing")))?,
+            "--f-number" => options.f_number = Some(value()?.parse().map_err(|_| invalid(String::from("bad --f-number")))?),
+            "--shutter" => options.shutter = Some(value()?.parse().map_err(|_| invalid(String::from("bad --shutter")))?),
+            "--iso" => options.iso = Some(value()?.parse().map_err(|_| invalid(String::from("bad --iso")))?),
+            "--width" => options.image_width = value()?.parse().map_err(|_| invalid(String::from("bad --width")))?,
+            "--spp" => options.samples_per_pixel = value()?.parse().map_err(|_| invalid(String::from("bad --spp")))?,
+            "--max-depth" => options.max_depth = Some(value()?.parse().map_err(|_| invalid(String::from("bad --max-depth")))?),
+            "--rr-depth" => options.rr_min_depth = value()?.parse().map_err(|_| invalid(String::from("bad --rr-depth")))?,
+            "--photons" => options.photons = value()?.parse().map_err(|_| invalid(String::from("bad --photons")))?,
+            "--photon-radius" => options.photon_radius = value()?.parse().map_err(|_| invalid(String::from("bad --photon-radius")))?,
+            "--bootstrap" => options.bootstrap = value()?.parse().map_err(|_| invalid(String::from("bad --bootstrap")))?,
+            "--seed" => options.seed = Some(value()?.parse().map_err(|_| invalid(String::from("bad --seed")))?),
+            "--camera-path" => options.camera_path = Some(value()?),
+            "--frames" => {
+                let range = value()?;
+                let (first, last) = range.split_once('-').unwrap_or((&range, &range));
+                let frame = |n: &str| n.parse::<i32>().ok().filter(|&n| n >= 1);
+                options.frames = match (frame(first), frame(last)) {
+                    (Some(first), Some(last)) if first <= last => Some((first, last)),
+                    _ => return Err(invalid(String::from("bad --frames"))),
+                };
+            }
+            "--fps" => options.fps = value()?.parse().map_err(|_| invalid(String::from("bad --fps")))?,
+            "--aovs" => {
+                let names = value()?;
+                options.aovs = match names.as_str() {
+                    "all" => Aov::ALL.to_vec(),
+                    _ => names
+                        .split(',')
+                        .map(|name| Aov::from_name(name).ok_or_else(|| invalid(format!("unknown AOV {}", name))))
+                        .collect::<std::io::Result<Vec<Aov>>>()?,
+                };
             }
+            "--denoise" => options.denoise = true,
+            "--crop" => {
+                let numbers = value()?
+                    .split(',')
+                    .map(|n| n.parse::<usize>().map_err(|_| invalid(String::from("bad --crop"))))
+                    .collect::<std::io::Result<Vec<usize>>>()?;
+                options.crop = match numbers[..] {
+                    [x, y, width, height] if width > 0 && height > 0 => Some((x, y, width, height)),
+                    _ => return Err(invalid(String::from("--crop takes x,y,width,height"))),
+                };
+            }
+            "--crop-base" => options.crop_base = Some(value()?),
+            "--stats" => options.stats = Some(value()?),
+            "--output" => options.output = Some(value()?),
+            _ => return Err(invalid(format!("unknown argument {}", flag))),
         }
     }
 
-    world.add(Arc::new(Sphere::new(
-        Point3::new(0.0, 1.0, 0.0),
-        1.0,
-        Box::new(Dielectric::new(1.5))
-    )));
-
-    world.add(Arc::new(Sphere::new(
-        Point3::new(-4.0, 1.0, 0.0),
-        1.0,
-        Box::new(Lambertian { albedo: Color::new(0.4, 0.2, 0.1) })
-    )));
-
-    world.add(Arc::new(Sphere::new(
-        Point3::new(4.0, 1.0, 0.0),
-        1.0,
-        Box::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0))
-    )));
-
-    world
+    Ok(options)
+}
+
+fn create_integrator (options: &Options, camera: &Arc<Camera>) -> std::io::Result<Arc<dyn Integrator>> {
+    // Russian roulette ends paths long before this, it only guards against runaways.
+    let max_depth = options.max_depth.unwrap_or(1024);
+
+    let integrator: Arc<dyn Integrator> = match options.integrator.as_str() {
+        "naive" => Arc::new(PathIntegrator {
+            max_depth,
+            rr_min_depth: options.rr_min_depth,
+            next_event: false,
+        }),
+        "path" => Arc::new(PathIntegrator {
+            max_depth,
+            rr_min_depth: options.rr_min_depth,
+            next_event: true,
+        }),
+        "spectral" => Arc::new(SpectralPathIntegrator {
+            max_depth,
+            rr_min_depth: options.rr_min_depth,
+        }),
+        "bdpt" => Arc::new(BdptIntegrator {
+            camera: Arc::clone(camera),
+            max_depth: options.max_depth.unwrap_or(8) as usize,
+        }),
+        // a photon pass for every sample per pixel
+        "photon" => Arc::new(PhotonIntegrator::new(
+            options.photons,
+            options.photon_radius,
+            options.max_depth.unwrap_or(16),
+            options.rr_min_depth,
+        )),
+        "mlt" => Arc::new(MltIntegrator::new(
+            Arc::clone(camera),
+            PathIntegrator {
+                max_depth,
+                rr_min_depth: options.rr_min_depth,
+                next_event: true,
+            },
+            options.bootstrap,
+            options.seed.unwrap_or_else(|| (random_f64_01() * u64::MAX as f64) as u64),
+        )),
+        "ao" => Arc::new(AmbientOcclusionIntegrator { samples: 4, max_distance: 2.0 }),
+        "whitted" => Arc::new(WhittedIntegrator { max_depth }),
+        "normals" => Arc::new(NormalIntegrator),
+        "depth" => Arc::new(DepthIntegrator { max_distance: 30.0 }),
+        "uv" => Arc::new(UvIntegrator),
+        "material-id" => Arc::new(MaterialIdIntegrator),
+        "bvh-cost" => Arc::new(BvhCostIntegrator { max_visits: 100 }),
+        name => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown integrator {}", name))),
+    };
+    Ok(integrator)
+}
+
+// Keyframed cameras: standing still, circling the origin once in four seconds, or
+// pushing in while widening the view over three.
+fn camera_path (name: Option<&str>, vfov: f64) -> std::io::Result<CameraPath> {
+    let lookfrom = Point3::new(13.0, 2.0, 3.0);
+    let lookat = Point3::new(0.0, 0.0, 0.0);
+    Ok(match name {
+        None => CameraPath::fixed(lookfrom, lookat, vfov, 10.0),
+        Some("orbit") => {
+            let radius = (13.0f64 * 13.0 + 3.0 * 3.0).sqrt();
+            let start = 3.0f64.atan2(13.0);
+            let keys = (0..=8)
+                .map(|i| {
+                    let angle = start + f64::from(i) * std::f64::consts::PI / 4.0;
+                    Key::new(f64::from(i) * 0.5, Point3::new(radius * angle.cos(), 2.0, radius * angle.sin()))
+                })
+                .collect();
+            CameraPath {
+                lookfrom: Track::new(Interpolation::CatmullRom, keys),
+                ..CameraPath::fixed(lookfrom, lookat, vfov, 10.0)
+            }
+        }
+        Some("dolly") => {
+            let near = Point3::new(6.0, 1.2, 1.4);
+            CameraPath {
+                lookfrom: Track::new(Interpolation::Bezier, vec![Key::new(0.0, lookfrom), Key::new(3.0, near)]),
+                lookat: Track::constant(lookat),
+                vfov: Track::new(Interpolation::Bezier, vec![Key::new(0.0, vfov), Key::new(3.0, vfov * 1.8)]),
+                focus_dist: Track::new(Interpolation::Bezier, vec![Key::new(0.0, 10.0), Key::new(3.0, 5.0)]),
+            }
+        }
+        Some(name) => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown camera path {}", name))),
+    })
 }
 
 pub fn entry() -> std::io::Result<()> {
-    let image_path = Path::new(".");
-    let mut image_file = File::create(image_path.join("dist").join("temp.ppm"))?;
+    let options = parse_args()?;
+    if let Some(seed) = options.seed {
+        set_seed(seed);
+    }
 
     // Image
-    let aspect_ratio = 3.0 / 2.0;
-    let image_width = 1200;
+    let stereo = match options.stereo.as_deref() {
+        None => None,
+        Some("side-by-side") => Some(StereoLayout::SideBySide),
+        Some("over-under") => Some(StereoLayout::OverUnder),
+        Some(name) => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown stereo layout {}", name))),
+    };
+    // of one eye's view
+    let eye_aspect = match options.camera.as_str() {
+        "fisheye" | "ods" => 1.0,
+        "equirect" => 2.0,
+        _ => 3.0 / 2.0,
+    };
+    let aspect_ratio = match stereo {
+        Some(StereoLayout::SideBySide) => eye_aspect * 2.0,
+        Some(StereoLayout::OverUnder) => eye_aspect / 2.0,
+        None => eye_aspect,
+    };
+    let image_width = options.image_width;
     let image_height = (f64::from(image_width) / aspect_ratio) as i32;
-    let samples_per_pixel = 200;
-    let max_depth = 50;
+    let samples_per_pixel = options.samples_per_pixel;
+    if let Some((x, y, width, height)) = options.crop {
+        if x + width > image_width as usize || y + height > image_height as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("crop window outside the {}x{} image", image_width, image_height),
+            ));
+        }
+    }
+    // A crop is put back into a full image from an earlier render when given one.
+    let base = match &options.crop_base {
+        Some(path) if options.crop.is_some() => {
+            let base = Image::load_linear(Path::new(path))?;
+            if base.width != image_width as usize || base.height != image_height as usize {
+                return Err(Error::new(ErrorKind::InvalidInput, format!("{} isn't {}x{}", path, image_width, image_height)));
+            }
+            Some(base)
+        }
+        Some(_) => return Err(Error::new(ErrorKind::InvalidInput, String::from("--crop-base needs --crop"))),
+        None => None,
+    };
 
     // World
-    let world = Arc::new(random_scene());
+    let world = Arc::new(match options.scene.as_str() {
+        "random" => random_scene(),
+        "lights" => {
+            let ies = match &options.ies {
+                Some(path) => Some(IesProfile::load(Path::new(path))?),
+                None => None,
+            };
+            lights_scene(ies)
+        }
+        "caustics" => caustics_scene(),
+        "glass" => glass_scene(),
+        "dispersion" => dispersion_scene(),
+        "thin-film" => thin_film_scene(),
+        "subsurface" => subsurface_scene(),
+        "layered" => layered_scene(),
+        "daylight" => daylight_scene(),
+        "animated" => animated_scene(),
+        "cutout" => cutout_scene(),
+        "bumps" => {
+            let normal_map = match &options.normal_map {
+                Some(path) => Some(ImageTexture::load(Path::new(path))?),
+                None => None,
+            };
+            bumps_scene(normal_map)
+        }
+        "smoke" => {
+            let grid = match &options.volume {
+                Some(path) if path.ends_with(".svdb") => Some(VoxelGrid::load_sparse(Path::new(path), smoke_bounds())?),
+                Some(path) => Some(VoxelGrid::load_raw(Path::new(path), smoke_bounds())?),
+                None => None,
+            };
+            smoke_scene(grid)
+        }
+        name => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown scene {}", name))),
+    });
 
     // Camera
-    let camera = Arc::new(Camera::new(
-        Point3::new(13.0, 2.0, 3.0),
-        Point3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        20.0,
-        aspect_ratio,
-        0.1,
-        10.0,
-    ));
-
-    write!(image_file, "P3\n{} {}\n255\n", image_width, image_height)?;
-
-    let thread_num = 4;
-    let mut thread_handlers = vec![];
-
-    // Prepare tasks
-    let mut tasks = vec![];
-    for j in (0..image_height).rev() {
-        for i in 0..image_width {
-            tasks.push((i, j, tasks.len()));
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let path = camera_path(options.camera_path.as_deref(), options.fov.unwrap_or(20.0))?;
+    // sunny 16 for whatever isn't given
+    let exposure = match (options.f_number, options.shutter, options.iso) {
+        (None, None, None) => None,
+        (f_number, shutter, iso) => Some(Exposure {
+            f_number: f_number.unwrap_or(16.0),
+            shutter_speed: shutter.unwrap_or(0.01),
+            iso: iso.unwrap_or(100.0),
+        }),
+    };
+    let aperture = || -> std::io::Result<Aperture> {
+        Ok(match (&options.aperture_mask, options.blades) {
+            (Some(path), _) => Aperture::mask(&ImageTexture::load(Path::new(path))?)?,
+            (None, Some(blades)) => Aperture::polygon(blades, 0.0),
+            (None, None) => Aperture::Circular,
+        })
+    };
+    let projection = |aspect_ratio: f64, focus_dist: f64| -> std::io::Result<Box<dyn CameraModel>> {
+        Ok(match options.camera.as_str() {
+            "orthographic" => Box::new(Orthographic { width: 6.0 * aspect_ratio, height: 6.0 }),
+            "fisheye" => Box::new(Fisheye { fov: options.fov.unwrap_or(180.0), aspect_ratio }),
+            "equirect" => Box::new(Equirectangular),
+            "cubemap" => Box::new(CubeMap),
+            "ods" => Box::new(Ods { interaxial: options.interaxial }),
+            // a 35mm full frame film
+            "realistic" => {
+                let lens = match &options.lens {
+                    Some(path) => Realistic::load(Path::new(path), 43.27, aspect_ratio, focus_dist, options.stop_diameter, aperture()?)?,
+                    None => Realistic::from_prescription(DOUBLE_GAUSS_50MM, 43.27, aspect_ratio, focus_dist, options.stop_diameter, aperture()?)?,
+                };
+                match exposure {
+                    Some(exposure) => Box::new(lens.with_f_number(exposure.f_number)),
+                    None => Box::new(lens),
+                }
+            }
+            name => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown camera {}", name))),
+        })
+    };
+    // The camera as it is `time` seconds in.
+    let camera_at = |time: f64| -> std::io::Result<Arc<Camera>> {
+        let frame = path.frame(time, vup);
+        let vfov = path.vfov.at(time);
+        let focus_dist = path.focus_dist.at(time);
+        let camera = match (stereo, options.camera.as_str()) {
+            (None, "perspective") => {
+                let lens = Perspective::new(vfov, aspect_ratio, options.aperture, focus_dist)
+                    .with_aperture(aperture()?)
+                    .with_shift(options.shift.0, options.shift.1)
+                    .with_tilt(options.tilt.0, options.tilt.1);
+                Camera::with_model(frame, match exposure {
+                    Some(exposure) => Box::new(lens.with_f_number(exposure.f_number)),
+                    None => Box::new(lens),
+                })
+            }
+            (None, _) => Camera::with_model(frame, projection(aspect_ratio, focus_dist)?),
+            (Some(_), "ods") => return Err(Error::new(ErrorKind::InvalidInput, String::from("ods renders both eyes already"))),
+            (Some(layout), "perspective") => Camera::with_model(frame, Box::new(Stereo::perspective(
+                vfov,
+                eye_aspect,
+                options.aperture,
+                focus_dist,
+                layout,
+                options.interaxial,
+                options.convergence,
+            )?)),
+            (Some(layout), _) => Camera::with_model(frame, Box::new(Stereo::new(
+                projection(eye_aspect, focus_dist)?,
+                projection(eye_aspect, focus_dist)?,
+                layout,
+                options.interaxial,
+            ))),
+        };
+        Ok(Arc::new(match exposure {
+            Some(exposure) => camera.with_exposure(exposure),
+            None => camera,
+        }))
+    };
+
+    // The denoiser is guided by albedo and normals, rendered whether asked for or not.
+    let mut aovs = options.aovs.clone();
+    if options.denoise {
+        for guide in [Aov::Albedo, Aov::Normal] {
+            if !aovs.contains(&guide) {
+                aovs.push(guide);
+            }
         }
     }
-    let task_count = Arc::new(tasks.len());
-    let tasks_mutex = Arc::new(Mutex::new(tasks));
-
-    let pixels_mutex = Arc::new(Mutex::new(HashMap::new()));
-
-    for _ in 0..thread_num {
-        let world_arc = Arc::clone(&world);
-        let camera_arc = Arc::clone(&camera);
-        let pixels_arc = Arc::clone(&pixels_mutex);
-        let mut tasks_arc = Arc::clone(&tasks_mutex);
-        let task_count_arc = Arc::clone(&task_count);
-
-        let render_handler = spawn(move || {
-            loop {
-                let mut tasks_guard = tasks_arc.lock().unwrap();
-                if let Some((i, j, u)) = tasks_guard.pop() {
-                    let mut pixel_color = Color::default();
-                    for _ in 0..samples_per_pixel {
-                        let u = (f64::from(i) + random_f64_01()) / f64::from(image_width - 1);
-                        let v = (f64::from(j) + random_f64_01()) / f64::from(image_height - 1);
-                        let r = camera_arc.get_ray(u, v);
-                        pixel_color += ray_color(&r, &world_arc, max_depth);
-                    }
-                    let mut pixels_guard = pixels_arc.lock().unwrap();
-                    pixels_guard.insert(u, format_color(&pixel_color, samples_per_pixel));
-                    eprint!("\r{}/{}", tasks_guard.len(), task_count_arc);
-                } else {
-                    break
-                }
+    // Integrators are made per frame, some of them keep the camera or the scene's photons.
+    // Ray counts and seconds of every render so far, for --stats.
+    let totals = std::cell::Cell::new((RayStats::default(), 0.0));
+    let render = |world: &Arc<Scene>, camera: &Arc<Camera>| -> std::io::Result<Vec<(String, Image)>> {
+        let mut renderer = Renderer::new(create_integrator(&options, camera)?, image_width, image_height)
+            .with_samples(samples_per_pixel)
+            .with_progress(true)
+            .with_aovs(aovs.clone());
+        if let Some((x, y, width, height)) = options.crop {
+            renderer = renderer.with_crop(x, y, width, height);
+        }
+        let framebuffer = renderer.render_framebuffer(world, camera);
+        let (mut stats, seconds) = totals.get();
+        stats += framebuffer.stats;
+        totals.set((stats, seconds + framebuffer.seconds));
+        let mut layers = renderer.resolve_layers(&framebuffer, camera);
+        if options.denoise {
+            let guide = |name: &str| &layers.iter().find(|(layer, _)| layer == name).expect("guide rendered").1;
+            layers[0].1 = Denoiser::default().denoise(&layers[0].1, guide("albedo"), guide("normal"));
+            layers.retain(|(name, _)| name == "beauty" || options.aovs.iter().any(|aov| aov.name() == name));
+        }
+        // AOVs have nothing to go back into and are left black around the crop
+        if let (Some(base), Some((x, y, _, _))) = (&base, options.crop) {
+            for (i, (_, layer)) in layers.iter_mut().enumerate() {
+                let mut full = if i == 0 { base.clone() } else { Image::new(base.width, base.height) };
+                full.paste(x, y, layer);
+                *layer = full;
             }
-        });
-        thread_handlers.push(render_handler);
+        }
+        Ok(layers)
+    };
+    // Animated objects keep moving while the shutter is open, which blurs them.
+    let shutter = exposure.map_or(0.0, |exposure| exposure.shutter_speed);
+    let mut world = world;
+    let (first, last) = match options.frames {
+        Some(range) => range,
+        None => {
+            if world.is_animated() {
+                Arc::get_mut(&mut world).expect("scene not shared yet").set_time(0.0, shutter);
+            }
+            let camera = camera_at(0.0)?;
+            let layers = render(&world, &camera)?;
+            let output = options.output.as_deref().unwrap_or("dist/temp.ppm");
+            write_layers(Path::new(output), &layers)?;
+            return write_stats(options.stats.as_deref(), totals.get());
+        }
+    };
+
+    // Numbered frames, frame 1 at time 0. The BVH is only rebuilt when objects move.
+    // An --output with an extension names the frames and picks their format, shot.exr
+    // giving shot_0001.exr and so on, otherwise it is the directory for frame_0001.png.
+    let output = Path::new(options.output.as_deref().unwrap_or("dist"));
+    let (directory, name, extension) = match (output.file_stem(), output.extension()) {
+        (Some(stem), Some(extension)) => (
+            output.parent().unwrap_or_else(|| Path::new("")),
+            stem.to_string_lossy().into_owned(),
+            extension.to_string_lossy().into_owned(),
+        ),
+        _ => (output, String::from("frame"), String::from("png")),
+    };
+    std::fs::create_dir_all(directory)?;
+    for frame in first..=last {
+        let time = f64::from(frame - 1) / options.fps;
+        if world.is_animated() {
+            Arc::get_mut(&mut world).expect("scene still shared between frames").set_time(time, shutter);
+        }
+        let camera = camera_at(time)?;
+        let layers = render(&world, &camera)?;
+        let output = directory.join(format!("{}_{:04}.{}", name, frame, extension));
+        write_layers(&output, &layers)?;
+        eprintln!("\rwrote {}", output.display());
     }
 
-    for handler in thread_handlers {
-        handler.join().unwrap();
+    write_stats(options.stats.as_deref(), totals.get())
+}
+
+// JSON of the ray statistics, summed over every frame, when asked for.
+fn write_stats (path: Option<&str>, (stats, seconds): (RayStats, f64)) -> std::io::Result<()> {
+    match path {
+        Some(path) => std::fs::write(path, stats.to_json(seconds)),
+        None => Ok(()),
     }
+}
 
-    let pixels_final = pixels_mutex.lock().unwrap();
-    for u in 0..*task_count {
-        if let Some(s) = pixels_final.get(&u) {
-            write!(image_file, "{}\n", s);
-        }
+// The beauty image followed by AOVs, all as layers of one file for .exr, otherwise
+// each AOV next to the image with its name before the extension.
+fn write_layers (path: &Path, layers: &[(String, Image)]) -> std::io::Result<()> {
+    if path.extension().is_some_and(|extension| extension == "exr") {
+        let named: Vec<(&str, &Image)> = layers
+            .iter()
+            .enumerate()
+            .map(|(i, (name, image))| (if i == 0 { "" } else { name.as_str() }, image))
+            .collect();
+        return std::fs::write(path, encode_exr(&named));
     }
 
+    layers[0].1.save(path)?;
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("image");
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("ppm");
+    for (name, image) in &layers[1..] {
+        image.save(&path.with_file_name(format!("{}.{}.{}", stem, name, extension)))?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
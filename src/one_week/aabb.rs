@@ -0,0 +1,60 @@
+use crate::common::ray::Ray;
+use crate::common::vec3::Point3;
+
+#[derive(Clone, Copy)]
+pub struct Aabb {
+    pub minimum: Point3,
+    pub maximum: Point3,
+}
+
+impl Aabb {
+    pub fn new (minimum: Point3, maximum: Point3) -> Self {
+        Self { minimum, maximum }
+    }
+
+    pub fn hit (&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.interval(r, t_min, t_max).is_some()
+    }
+
+    // Part of [t_min, t_max] the ray spends inside the box.
+    pub fn interval (&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> Option<(f64, f64)> {
+        let axes = [
+            (self.minimum.x, self.maximum.x, r.origin.x, r.direction.x),
+            (self.minimum.y, self.maximum.y, r.origin.y, r.direction.y),
+            (self.minimum.z, self.maximum.z, r.origin.z, r.direction.z),
+        ];
+        for (min, max, origin, direction) in axes.iter() {
+            let inv_d = 1.0 / direction;
+            let mut t0 = (min - origin) * inv_d;
+            let mut t1 = (max - origin) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+
+    pub fn centroid (&self) -> Point3 {
+        (self.minimum + self.maximum) * 0.5
+    }
+}
+
+pub fn surrounding_box (box0: &Aabb, box1: &Aabb) -> Aabb {
+    Aabb {
+        minimum: Point3::new(
+            box0.minimum.x.min(box1.minimum.x),
+            box0.minimum.y.min(box1.minimum.y),
+            box0.minimum.z.min(box1.minimum.z),
+        ),
+        maximum: Point3::new(
+            box0.maximum.x.max(box1.maximum.x),
+            box0.maximum.y.max(box1.maximum.y),
+            box0.maximum.z.max(box1.maximum.z),
+        ),
+    }
+}
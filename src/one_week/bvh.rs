@@ -0,0 +1,111 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::common::ray::Ray;
+use crate::common::stats;
+use crate::common::vec3::Color;
+use crate::one_week::aabb::{surrounding_box, Aabb};
+use crate::one_week::hittable::{HitRecord, Hittable};
+use crate::one_week::material::Material;
+
+pub struct BvhNode {
+    pub left: Arc<dyn Hittable>,
+    pub right: Arc<dyn Hittable>,
+    pub bbox: Aabb,
+}
+
+impl BvhNode {
+    // Panics if the slice is empty or holds an object without a bounding box.
+    pub fn new (objects: &mut [Arc<dyn Hittable>], time0: f64, time1: f64) -> Self {
+        let boxes: Vec<Aabb> = objects.iter()
+            .map(|object| object.bounding_box(time0, time1).expect("no bounding box in BvhNode constructor"))
+            .collect();
+
+        let mut centroid_box = Aabb::new(boxes[0].centroid(), boxes[0].centroid());
+        for b in boxes.iter() {
+            centroid_box = surrounding_box(&centroid_box, &Aabb::new(b.centroid(), b.centroid()));
+        }
+        let extent = centroid_box.maximum - centroid_box.minimum;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        };
+
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            1 => (objects[0].clone(), objects[0].clone()),
+            2 => (objects[0].clone(), objects[1].clone()),
+            _ => {
+                objects.sort_by(|a, b| box_compare(a, b, axis, time0, time1));
+                let mid = objects.len() / 2;
+                let (lower, upper) = objects.split_at_mut(mid);
+                (
+                    Arc::new(BvhNode::new(lower, time0, time1)),
+                    Arc::new(BvhNode::new(upper, time0, time1)),
+                )
+            }
+        };
+
+        let bbox = boxes.iter().skip(1).fold(boxes[0], |acc, b| surrounding_box(&acc, b));
+
+        Self { left, right, bbox }
+    }
+}
+
+fn box_compare (a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize, time0: f64, time1: f64) -> Ordering {
+    let centroid = |object: &Arc<dyn Hittable>| {
+        let c = object.bounding_box(time0, time1).unwrap().centroid();
+        match axis {
+            0 => c.x,
+            1 => c.y,
+            _ => c.z,
+        }
+    };
+    centroid(a).partial_cmp(&centroid(b)).unwrap_or(Ordering::Equal)
+}
+
+impl Hittable for BvhNode {
+    fn hit (&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        stats::node_visit();
+
+        if !self.bbox.hit(r, t_min, t_max) {
+            return None;
+        }
+
+        let hit_left = self.left.hit(r, t_min, t_max);
+        let right_max = match &hit_left {
+            Some(rec) => rec.t,
+            None => t_max,
+        };
+        match self.right.hit(r, t_min, right_max) {
+            Some(rec) => Some(rec),
+            None => hit_left,
+        }
+    }
+
+    fn bounding_box (&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+
+    fn materials (&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.left.materials(f);
+        self.right.materials(f);
+    }
+
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        stats::node_visit();
+
+        if !self.bbox.hit(r, t_min, t_max) {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+
+        let left = self.left.transmittance(r, t_min, t_max);
+        // Single object nodes hold it on both sides.
+        if left.near_zero() || Arc::ptr_eq(&self.left, &self.right) {
+            return left;
+        }
+        left * self.right.transmittance(r, t_min, t_max)
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use crate::common::ray::Ray;
+use crate::common::spectrum::{Spectrum, Wavelengths};
+use crate::common::vec3::{Color, Vec3};
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::material::Material;
+use crate::one_week::medium::MediumStack;
+use crate::one_week::texture::Texture;
+
+// `base` with holes, as in leaves and fences: where `opacity` is zero rays go straight
+// through, where it is partial they stop with that chance. Anything cut this way is
+// best thought of as a thin surface, shadow rays see the holes but not the inside.
+pub struct Cutout {
+    pub base: Arc<dyn Material>,
+    pub opacity: Arc<dyn Texture>,
+}
+
+impl Cutout {
+    pub fn new (base: Arc<dyn Material>, opacity: Arc<dyn Texture>) -> Self {
+        Self { base, opacity }
+    }
+}
+
+impl Material for Cutout {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.base.scatter(r_in, rec)
+    }
+
+    fn eval (&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        self.base.eval(r_in, rec, direction)
+    }
+
+    fn pdf (&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.base.pdf(r_in, rec, direction)
+    }
+
+    fn scatter_in (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack) -> Option<(Color, Ray)> {
+        self.base.scatter_in(r_in, rec, media)
+    }
+
+    fn scatter_spectral (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambdas: &mut Wavelengths,
+    ) -> Option<(Spectrum, Ray)> {
+        self.base.scatter_spectral(r_in, rec, media, lambdas)
+    }
+
+    fn transmission (&self, distance: f64) -> Color {
+        self.base.transmission(distance)
+    }
+
+    fn opacity (&self, rec: &HitRecord) -> f64 {
+        self.opacity.value(rec.u, rec.v, &rec.p).x.clamp(0.0, 1.0) * self.base.opacity(rec)
+    }
+
+    fn perturb (&self, rec: &mut HitRecord) {
+        self.base.perturb(rec);
+    }
+
+    fn is_specular (&self) -> bool {
+        self.base.is_specular()
+    }
+
+    fn scatter_lobe (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray, bool)> {
+        self.base.scatter_lobe(r_in, rec)
+    }
+}
@@ -1,16 +1,29 @@
 use std::sync::Arc;
 
-use crate::common::vec3::{Point3, Vec3};
+use crate::common::vec3::{Color, Point3, Vec3};
 use crate::common::ray::Ray;
 use crate::common::vec3_opts::dot;
+use crate::one_week::aabb::Aabb;
 use crate::one_week::material::{Material, NullMaterial};
+use crate::one_week::random_f64_01;
 
+// `normal` is the geometric normal and `shading_normal` the one materials shade with,
+// which normal and bump maps tilt. Both face against the ray. `dpdu` and `dpdv` are
+// the surface's tangents along its (u, v) parameterisation, zero where there is none.
+// `object_id` numbers the scene's top level objects from 1, 0 outside a scene.
+#[derive(Clone)]
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
+    pub shading_normal: Vec3,
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub material: Arc<dyn Material>,
     pub t: f64,
-    pub front_face: bool
+    pub u: f64,
+    pub v: f64,
+    pub front_face: bool,
+    pub object_id: usize,
 }
 
 impl Default for HitRecord {
@@ -18,9 +31,15 @@ impl Default for HitRecord {
         Self {
             p: Point3::default(),
             normal: Vec3::default(),
+            shading_normal: Vec3::default(),
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
             material: Arc::new(NullMaterial {}),
             t: 0.0,
-            front_face: false
+            u: 0.0,
+            v: 0.0,
+            front_face: false,
+            object_id: 0,
         }
     }
 }
@@ -29,13 +48,34 @@ impl HitRecord {
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
         self.front_face = dot(&r.direction, outward_normal) < 0.0;
         self.normal = if self.front_face {
-            outward_normal.clone()
+            *outward_normal
         } else {
-            -outward_normal.clone()
-        }
+            -*outward_normal
+        };
+        self.shading_normal = self.normal;
+    }
+
+    // Whether the ray slips through a cutout here, by chance where the surface is partly opaque.
+    pub fn cut_out (&self) -> bool {
+        let opacity = self.material.opacity(self);
+        opacity < 1.0 && random_f64_01() >= opacity
     }
 }
 
 pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb>;
+
+    // Fraction of light that gets through between t_min and t_max, for shadow rays.
+    // Surfaces are opaque, media let part of it pass.
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        match self.hit(r, t_min, t_max) {
+            Some(_) => Color::default(),
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    // Calls `f` with every material hits on the object can carry, in the order the
+    // object was put together.
+    fn materials (&self, _f: &mut dyn FnMut(&Arc<dyn Material>)) {}
 }
\ No newline at end of file
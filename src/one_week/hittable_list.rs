@@ -1,7 +1,10 @@
 use std::sync::Arc;
 
 use crate::common::ray::Ray;
+use crate::common::vec3::Color;
+use crate::one_week::aabb::{surrounding_box, Aabb};
 use crate::one_week::hittable::{Hittable, HitRecord};
+use crate::one_week::material::Material;
 
 pub struct HittableList {
     pub objects: Vec<Arc<dyn Hittable>>
@@ -27,4 +30,35 @@ impl Hittable for HittableList {
 
         hit_anything
     }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        let mut output_box: Option<Aabb> = None;
+
+        for object in self.objects.iter() {
+            let temp_box = object.bounding_box(time0, time1)?;
+            output_box = match output_box {
+                Some(b) => Some(surrounding_box(&b, &temp_box)),
+                None => Some(temp_box),
+            };
+        }
+
+        output_box
+    }
+
+    fn materials (&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        for object in self.objects.iter() {
+            object.materials(f);
+        }
+    }
+
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        let mut transmittance = Color::new(1.0, 1.0, 1.0);
+        for object in self.objects.iter() {
+            transmittance *= object.transmittance(r, t_min, t_max);
+            if transmittance.near_zero() {
+                break;
+            }
+        }
+        transmittance
+    }
 }
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use crate::common::ray::Ray;
+use crate::common::spectrum::{Spectrum, Wavelengths};
+use crate::common::vec3::{Color, Vec3};
+use crate::common::vec3_opts::{dot, random_in_unit_sphere, reflect, unit_vector};
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::material::{Dielectric, Material};
+use crate::one_week::medium::MediumStack;
+use crate::one_week::random_f64_01;
+use crate::one_week::texture::{SolidColor, Texture};
+
+// Blend of two materials, `second` weighted by `amount` and `first` by the rest.
+// Scattering picks one of them with that probability, so the choice needs no extra
+// weight, while light sampling evaluates both. A specular part adds nothing to
+// `eval` and `pdf`, and its samples are flagged by `scatter_lobe`.
+pub struct MixMaterial {
+    pub first: Arc<dyn Material>,
+    pub second: Arc<dyn Material>,
+    pub amount: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    pub fn new (first: Arc<dyn Material>, second: Arc<dyn Material>, amount: f64) -> Self {
+        Self::with_mask(first, second, Arc::new(SolidColor::scalar(amount)))
+    }
+
+    pub fn with_mask (first: Arc<dyn Material>, second: Arc<dyn Material>, amount: Arc<dyn Texture>) -> Self {
+        Self { first, second, amount }
+    }
+
+    fn amount (&self, rec: &HitRecord) -> f64 {
+        self.amount.value(rec.u, rec.v, &rec.p).x.clamp(0.0, 1.0)
+    }
+
+    fn choose (&self, rec: &HitRecord) -> &Arc<dyn Material> {
+        if random_f64_01() < self.amount(rec) {
+            &self.second
+        } else {
+            &self.first
+        }
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.choose(rec).scatter(r_in, rec)
+    }
+
+    fn eval (&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let t = self.amount(rec);
+        self.first.eval(r_in, rec, direction) * (1.0 - t) + self.second.eval(r_in, rec, direction) * t
+    }
+
+    fn pdf (&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let t = self.amount(rec);
+        self.first.pdf(r_in, rec, direction) * (1.0 - t) + self.second.pdf(r_in, rec, direction) * t
+    }
+
+    fn scatter_in (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack) -> Option<(Color, Ray)> {
+        self.choose(rec).scatter_in(r_in, rec, media)
+    }
+
+    fn scatter_spectral (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambdas: &mut Wavelengths,
+    ) -> Option<(Spectrum, Ray)> {
+        self.choose(rec).scatter_spectral(r_in, rec, media, lambdas)
+    }
+
+    fn is_specular (&self) -> bool {
+        self.first.is_specular() && self.second.is_specular()
+    }
+
+    fn scatter_lobe (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray, bool)> {
+        self.choose(rec).scatter_lobe(r_in, rec)
+    }
+}
+
+// A clear coat of index `ior` over `base`, like varnish on wood or lacquer on car
+// paint. Each path either reflects off the coat, with the chance given by its
+// Fresnel reflectance, or passes through to the base and is dimmed by the coat on
+// the way back out, so the layers never reflect more than arrives. `tint` is the
+// colour the coat absorbs on a round trip at normal incidence, `roughness` blurs
+// the coat's reflection.
+pub struct Coated {
+    pub base: Arc<dyn Material>,
+    pub ior: f64,
+    pub roughness: f64,
+    pub tint: Color,
+}
+
+impl Coated {
+    pub fn new (base: Arc<dyn Material>, ior: f64, roughness: f64, tint: Color) -> Self {
+        Self {
+            base,
+            ior,
+            roughness: roughness.min(1.0),
+            tint,
+        }
+    }
+
+    fn fresnel (&self, cosine: f64) -> f64 {
+        Dielectric::reflectance(cosine.clamp(0.0, 1.0), 1.0 / self.ior)
+    }
+
+    // What the coat leaves of light that went through it in at `cos_in` and out at `cos_out`.
+    fn through_coat (&self, cos_in: f64, cos_out: f64) -> Color {
+        let cos_in = cos_in.abs().max(0.05);
+        let cos_out = cos_out.abs().max(0.05);
+        let length = 0.5 / cos_in + 0.5 / cos_out;
+        Color::new(self.tint.x.powf(length), self.tint.y.powf(length), self.tint.z.powf(length))
+            * (1.0 - self.fresnel(cos_out))
+    }
+
+    fn cos_in (r_in: &Ray, rec: &HitRecord) -> f64 {
+        dot(&-unit_vector(&r_in.direction), &rec.shading_normal)
+    }
+
+    // Reflection off the coat, or None when the path goes on to the base.
+    fn reflect_coat (&self, r_in: &Ray, rec: &HitRecord) -> Option<Ray> {
+        if random_f64_01() >= self.fresnel(Self::cos_in(r_in, rec)) {
+            return None;
+        }
+        let reflected = reflect(&unit_vector(&r_in.direction), &rec.shading_normal);
+        Some(Ray::new(&rec.p, &(reflected + random_in_unit_sphere() * self.roughness)).with_time(r_in.time))
+    }
+
+    fn base_weight (&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.through_coat(Self::cos_in(r_in, rec), dot(&unit_vector(&scattered.direction), &rec.shading_normal))
+    }
+}
+
+impl Material for Coated {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.scatter_in(r_in, rec, &mut MediumStack::default())
+    }
+
+    // Only the base responds to light sampling, `scatter_lobe` flags the coat.
+    fn eval (&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let cos_in = Self::cos_in(r_in, rec);
+        self.base.eval(r_in, rec, direction)
+            * self.through_coat(cos_in, dot(direction, &rec.shading_normal))
+            * (1.0 - self.fresnel(cos_in))
+    }
+
+    fn pdf (&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.base.pdf(r_in, rec, direction) * (1.0 - self.fresnel(Self::cos_in(r_in, rec)))
+    }
+
+    fn scatter_in (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack) -> Option<(Color, Ray)> {
+        if let Some(scattered) = self.reflect_coat(r_in, rec) {
+            return Some((Color::new(1.0, 1.0, 1.0), scattered));
+        }
+        let (attenuation, scattered) = self.base.scatter_in(r_in, rec, media)?;
+        Some((attenuation * self.base_weight(r_in, rec, &scattered), scattered))
+    }
+
+    fn scatter_spectral (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambdas: &mut Wavelengths,
+    ) -> Option<(Spectrum, Ray)> {
+        if let Some(scattered) = self.reflect_coat(r_in, rec) {
+            return Some((Spectrum::constant(1.0), scattered));
+        }
+        let (attenuation, scattered) = self.base.scatter_spectral(r_in, rec, media, lambdas)?;
+        Some((attenuation * Spectrum::from_rgb(&self.base_weight(r_in, rec, &scattered), lambdas), scattered))
+    }
+
+    // Light sampling reaches the base through the coat, unless the base is specular too.
+    fn is_specular (&self) -> bool {
+        self.base.is_specular()
+    }
+
+    // The coat's reflection is outside `eval` and `pdf`, blurred or not.
+    fn scatter_lobe (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray, bool)> {
+        if let Some(scattered) = self.reflect_coat(r_in, rec) {
+            return Some((Color::new(1.0, 1.0, 1.0), scattered, true));
+        }
+        let (attenuation, scattered, specular) = self.base.scatter_lobe(r_in, rec)?;
+        Some((attenuation * self.base_weight(r_in, rec, &scattered), scattered, specular))
+    }
+}
@@ -0,0 +1,365 @@
+use std::f64::consts::PI;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::common::onb::Onb;
+use crate::common::ray::Ray;
+use crate::common::vec3::{Color, Point3, Vec3};
+use crate::common::vec3_opts::{dot, random_in_unit_disk, random_unit_vector, unit_vector};
+use crate::one_week::{clamp, random_f64_01};
+
+pub struct LightSample {
+    // unit direction from the shaded point towards the light
+    pub wi: Vec3,
+    pub distance: f64,
+    pub radiance: Color,
+}
+
+// Start of a light subpath, `ray.direction` is a unit vector.
+pub struct LightEmission {
+    pub ray: Ray,
+    // radiant intensity along the ray, irradiance for directional lights
+    pub intensity: Color,
+    pub pdf_pos: f64,
+    pub pdf_dir: f64,
+}
+
+// All lights are delta lights: a point or a single direction, so rays never hit them.
+pub trait Light: Send + Sync {
+    fn sample_li (&self, p: &Point3) -> Option<LightSample>;
+
+    // `center` and `radius` bound the scene, directional lights shine from a disk covering it.
+    fn sample_le (&self, center: &Point3, radius: f64) -> Option<LightEmission>;
+
+    // Solid angle density of `sample_le` choosing the unit `direction`.
+    fn pdf_le (&self, direction: &Vec3) -> f64;
+
+    fn is_infinite (&self) -> bool {
+        false
+    }
+}
+
+pub struct LightList {
+    pub lights: Vec<Arc<dyn Light>>
+}
+
+impl LightList {
+    pub fn add (&mut self, light: Arc<dyn Light>) {
+        self.lights.push(light);
+    }
+}
+
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Color,
+    pub profile: Option<Arc<IesProfile>>,
+    // direction of the profile's zero vertical angle
+    pub axis: Vec3,
+}
+
+impl PointLight {
+    pub fn new (position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+            profile: None,
+            axis: Vec3::new(0.0, -1.0, 0.0),
+        }
+    }
+
+    pub fn with_profile (mut self, profile: Arc<IesProfile>, axis: Vec3) -> Self {
+        self.profile = Some(profile);
+        self.axis = unit_vector(&axis);
+        self
+    }
+
+    // `direction` points away from the light.
+    fn profile_scale (&self, direction: &Vec3) -> f64 {
+        match &self.profile {
+            Some(profile) => {
+                let cos_theta = clamp(dot(direction, &self.axis), -1.0, 1.0);
+                profile.scale(cos_theta.acos().to_degrees())
+            }
+            None => 1.0
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li (&self, p: &Point3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.length_squared();
+        let distance = distance_squared.sqrt();
+        let wi = to_light / distance;
+
+        let scale = self.profile_scale(&-wi);
+        if scale <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            wi,
+            distance,
+            radiance: self.intensity * scale / distance_squared,
+        })
+    }
+
+    fn sample_le (&self, _center: &Point3, _radius: f64) -> Option<LightEmission> {
+        let direction = random_unit_vector();
+        let intensity = self.intensity * self.profile_scale(&direction);
+        if intensity.near_zero() {
+            return None;
+        }
+
+        Some(LightEmission {
+            ray: Ray::new(&self.position, &direction),
+            intensity,
+            pdf_pos: 1.0,
+            pdf_dir: 1.0 / (4.0 * PI),
+        })
+    }
+
+    fn pdf_le (&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+pub struct SpotLight {
+    pub position: Point3,
+    pub direction: Vec3,
+    pub intensity: Color,
+    pub cos_total_width: f64,
+    pub cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    pub fn new (
+        position: Point3,
+        target: Point3,
+        intensity: Color,
+        total_width: f64,
+        falloff_start: f64,
+    ) -> Self {
+        Self {
+            position,
+            direction: unit_vector(&(target - position)),
+            intensity,
+            cos_total_width: total_width.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(total_width).to_radians().cos(),
+        }
+    }
+
+    fn falloff (&self, cos_theta: f64) -> f64 {
+        if cos_theta < self.cos_total_width {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        let delta = (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        delta * delta * (3.0 - 2.0 * delta)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li (&self, p: &Point3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.length_squared();
+        let distance = distance_squared.sqrt();
+        let wi = to_light / distance;
+
+        let falloff = self.falloff(dot(&-wi, &self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            wi,
+            distance,
+            radiance: self.intensity * falloff / distance_squared,
+        })
+    }
+
+    fn sample_le (&self, _center: &Point3, _radius: f64) -> Option<LightEmission> {
+        let cos_theta = 1.0 - random_f64_01() * (1.0 - self.cos_total_width);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_f64_01();
+        let direction = Onb::build_from_w(&self.direction).local(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            cos_theta,
+        );
+
+        Some(LightEmission {
+            ray: Ray::new(&self.position, &direction),
+            intensity: self.intensity * self.falloff(cos_theta),
+            pdf_pos: 1.0,
+            pdf_dir: 1.0 / (2.0 * PI * (1.0 - self.cos_total_width)),
+        })
+    }
+
+    fn pdf_le (&self, direction: &Vec3) -> f64 {
+        if dot(direction, &self.direction) < self.cos_total_width {
+            return 0.0;
+        }
+        1.0 / (2.0 * PI * (1.0 - self.cos_total_width))
+    }
+}
+
+pub struct DirectionalLight {
+    // direction the light travels in
+    pub direction: Vec3,
+    pub radiance: Color,
+}
+
+impl DirectionalLight {
+    pub fn new (direction: Vec3, radiance: Color) -> Self {
+        Self {
+            direction: unit_vector(&direction),
+            radiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_li (&self, _p: &Point3) -> Option<LightSample> {
+        Some(LightSample {
+            wi: -self.direction,
+            distance: f64::INFINITY,
+            radiance: self.radiance,
+        })
+    }
+
+    fn sample_le (&self, center: &Point3, radius: f64) -> Option<LightEmission> {
+        let frame = Onb::build_from_w(&self.direction);
+        let d = random_in_unit_disk() * radius;
+        let origin = *center - self.direction * radius + frame.u * d.x + frame.v * d.y;
+
+        Some(LightEmission {
+            ray: Ray::new(&origin, &self.direction),
+            intensity: self.radiance,
+            pdf_pos: 1.0 / (PI * radius * radius),
+            pdf_dir: 1.0,
+        })
+    }
+
+    fn pdf_le (&self, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn is_infinite (&self) -> bool {
+        true
+    }
+}
+
+// Rotationally symmetric candela distribution read from an IES LM-63 file.
+// Horizontal angles are averaged, and values are normalised so the peak is 1.
+pub struct IesProfile {
+    vertical_angles: Vec<f64>,
+    values: Vec<f64>,
+}
+
+impl IesProfile {
+    pub fn load (path: &Path) -> std::io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse (text: &str) -> std::io::Result<Self> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("ies: {}", msg));
+
+        let mut lines = text.lines();
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => break line.trim()[5..].to_string(),
+                Some(_) => continue,
+                None => return Err(invalid("missing TILT line")),
+            }
+        };
+
+        let mut numbers = Vec::new();
+        for token in lines.flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ',')) {
+            if token.is_empty() {
+                continue;
+            }
+            numbers.push(token.parse::<f64>().map_err(|_| invalid("bad number"))?);
+        }
+        let mut numbers = numbers.into_iter();
+        let next = |numbers: &mut std::vec::IntoIter<f64>| numbers.next().ok_or_else(|| invalid("unexpected end of file"));
+        // A whole number of things, each needing at least one of the numbers left.
+        let count = |numbers: &mut std::vec::IntoIter<f64>, msg: &str| -> std::io::Result<usize> {
+            let value = next(numbers)?;
+            if value < 0.0 || value.fract() != 0.0 || value > numbers.len() as f64 {
+                return Err(invalid(msg));
+            }
+            Ok(value as usize)
+        };
+
+        if tilt == "INCLUDE" {
+            let _geometry = next(&mut numbers)?;
+            let pairs = count(&mut numbers, "bad tilt table")?;
+            for _ in 0..pairs {
+                next(&mut numbers)?;
+                next(&mut numbers)?;
+            }
+        }
+
+        let _lamps = next(&mut numbers)?;
+        let _lumens = next(&mut numbers)?;
+        let multiplier = next(&mut numbers)?;
+        let vertical_count = count(&mut numbers, "bad angle count")?;
+        let horizontal_count = count(&mut numbers, "bad angle count")?;
+        for _ in 0..8 {
+            next(&mut numbers)?;
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid("empty angle table"));
+        }
+
+        let mut vertical_angles: Vec<f64> = Vec::with_capacity(vertical_count);
+        for _ in 0..vertical_count {
+            let angle = next(&mut numbers)?;
+            // `scale` looks angles up in order
+            if vertical_angles.last().is_some_and(|&last| angle <= last) {
+                return Err(invalid("vertical angles not ascending"));
+            }
+            vertical_angles.push(angle);
+        }
+        for _ in 0..horizontal_count {
+            next(&mut numbers)?;
+        }
+
+        let mut values = vec![0.0; vertical_count];
+        for _ in 0..horizontal_count {
+            for value in values.iter_mut() {
+                *value += next(&mut numbers)? * multiplier / horizontal_count as f64;
+            }
+        }
+
+        let max = values.iter().cloned().fold(0.0, f64::max);
+        if max <= 0.0 {
+            return Err(invalid("profile has no output"));
+        }
+        for value in values.iter_mut() {
+            *value /= max;
+        }
+
+        Ok(Self { vertical_angles, values })
+    }
+
+    pub fn scale (&self, theta: f64) -> f64 {
+        let angles = &self.vertical_angles;
+        if theta < angles[0] || theta > angles[angles.len() - 1] {
+            return 0.0;
+        }
+        for i in 1..angles.len() {
+            if theta <= angles[i] {
+                let span = angles[i] - angles[i - 1];
+                let t = if span > 0.0 { (theta - angles[i - 1]) / span } else { 0.0 };
+                return self.values[i - 1] * (1.0 - t) + self.values[i] * t;
+            }
+        }
+        self.values[0]
+    }
+}
@@ -1,18 +1,81 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
 use crate::common::ray::Ray;
+use crate::common::spectrum::{Spectrum, Wavelengths};
 use crate::common::vec3::{Color, Vec3};
 use crate::common::vec3_opts::{random_unit_vector, unit_vector, dot, reflect, refract, random_in_unit_sphere};
 
 use crate::one_week::random_f64_01;
 use crate::one_week::hittable::HitRecord;
+use crate::one_week::medium::{Medium, MediumStack};
 
 pub trait Material: Send + Sync {
     fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)>;
+
+    // BSDF times cosine for light arriving from the unit `direction`. Specular
+    // materials keep the default, a delta light never lines up with a delta lobe.
+    fn eval (&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> Color {
+        Color::default()
+    }
+
+    // Solid angle density of `scatter` choosing the unit `direction`.
+    fn pdf (&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // `scatter` for a path travelling through `media`. Interfaces between media
+    // override it to refract by the indices on both sides and update the stack.
+    fn scatter_in (&self, r_in: &Ray, rec: &HitRecord, _media: &mut MediumStack) -> Option<(Color, Ray)> {
+        self.scatter(r_in, rec)
+    }
+
+    // `scatter_in` at the path's wavelengths for spectral rendering. RGB attenuation
+    // is upsampled by default, wavelength dependent materials override it.
+    fn scatter_spectral (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambdas: &mut Wavelengths,
+    ) -> Option<(Spectrum, Ray)> {
+        self.scatter_in(r_in, rec, media)
+            .map(|(attenuation, scattered)| (Spectrum::from_rgb(&attenuation, lambdas), scattered))
+    }
+
+    // Fraction of a shadow ray that crosses `distance` through an object made of this
+    // material, refraction at its boundary ignored. Opaque by default.
+    fn transmission (&self, _distance: f64) -> Color {
+        Color::default()
+    }
+
+    // Chance that a ray stops at this hit instead of passing through a hole cut into
+    // the surface. Primitives skip hits that fail it, shadow rays included.
+    fn opacity (&self, _rec: &HitRecord) -> f64 {
+        1.0
+    }
+
+    // Tilts the shading normal of a fresh hit, for normal and bump maps. Primitives
+    // call it once they have filled in the record.
+    fn perturb (&self, _rec: &mut HitRecord) {}
+
+    // Specular materials scatter into nothing but delta lobes, so light sampling can't
+    // reach them.
+    fn is_specular (&self) -> bool {
+        false
+    }
+
+    // `scatter` that also says whether the direction came from a lobe `eval` and `pdf`
+    // leave out, such as a delta lobe. Materials with lobes of both kinds override it.
+    fn scatter_lobe (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray, bool)> {
+        self.scatter(r_in, rec).map(|(attenuation, scattered)| (attenuation, scattered, self.is_specular()))
+    }
 }
 
 pub struct NullMaterial;
 
 impl Material for NullMaterial {
-    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+    fn scatter (&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Ray)> {
         None
     }
 }
@@ -23,17 +86,29 @@ pub struct Lambertian {
 
 impl Material for Lambertian {
     fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let mut scatter_direction = rec.normal + random_unit_vector();
+        let mut scatter_direction = rec.shading_normal + random_unit_vector();
 
         if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
+            scatter_direction = rec.shading_normal;
         }
 
         Some((
-            self.albedo.clone(),
-            Ray::new(&rec.p, &scatter_direction)
+            self.albedo,
+            Ray::new(&rec.p, &scatter_direction).with_time(r_in.time)
         ))
     }
+
+    fn eval (&self, _r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let cosine = dot(&rec.shading_normal, direction);
+        if cosine <= 0.0 {
+            return Color::default();
+        }
+        self.albedo * cosine / PI
+    }
+
+    fn pdf (&self, _r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        dot(&rec.shading_normal, direction).max(0.0) / PI
+    }
 }
 
 pub struct Metal {
@@ -52,56 +127,115 @@ impl Metal {
 
 impl Material for Metal {
     fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let reflected = reflect(&unit_vector(&r_in.direction), &rec.normal);
+        let reflected = reflect(&unit_vector(&r_in.direction), &rec.shading_normal);
         Some((
-            self.albedo.clone(),
-            Ray::new(&rec.p, &(reflected + random_in_unit_sphere() * self.fuzz)),
+            self.albedo,
+            Ray::new(&rec.p, &(reflected + random_in_unit_sphere() * self.fuzz)).with_time(r_in.time),
         ))
     }
+
+    fn is_specular (&self) -> bool {
+        true
+    }
 }
 
+// Interface into the medium `inside`. The far side is `outside` when given, otherwise
+// whatever medium the path was in, so touching or nested objects refract by the ratio
+// of the indices on either side rather than against vacuum.
 pub struct Dielectric {
-    pub ir: f64
+    pub inside: Arc<Medium>,
+    pub outside: Option<Arc<Medium>>,
 }
 
 impl Dielectric {
     pub fn new (index_of_refraction: f64) -> Self {
-        Self {
-            ir: index_of_refraction
-        }
+        Self::with_media(Arc::new(Medium::new(index_of_refraction, Color::default())), None)
+    }
+
+    pub fn with_media (inside: Arc<Medium>, outside: Option<Arc<Medium>>) -> Self {
+        Self { inside, outside }
     }
 
-    fn reflectance (cosine: f64, ref_idx: f64) -> f64 {
-        let mut r0 = (1.0 - ref_idx) - (1.0 + ref_idx);
+    pub fn reflectance (cosine: f64, ref_idx: f64) -> f64 {
+        let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         r0 = r0 * r0;
         r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
     }
-}
 
-impl Material for Dielectric {
-    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let refraction_ratio = if rec.front_face {
-            1.0 / self.ir
-        } else {
-            self.ir
+    // Indices of refraction on the incident and the far side at wavelength `lambda`, or
+    // the RGB indices without one, and whether either of them depends on the wavelength.
+    pub fn indices (&self, rec: &HitRecord, media: &MediumStack, lambda: Option<f64>) -> (f64, f64, bool) {
+        let outside = match &self.outside {
+            Some(outside) => Some(outside),
+            None if rec.front_face => media.current(),
+            None => media.outside_of(&self.inside),
         };
+        let outside_ior = outside.map_or(1.0, |medium| medium.ior_at(lambda));
+        let inside_ior = self.inside.ior_at(lambda);
+        let dispersive = self.inside.dispersion.is_some() || outside.is_some_and(|medium| medium.dispersion.is_some());
+        if rec.front_face {
+            (outside_ior, inside_ior, dispersive)
+        } else {
+            (inside_ior, outside_ior, dispersive)
+        }
+    }
 
+    // Mirror reflection, or refraction by `refraction_ratio` into the far side's medium
+    // when `reflect` is false and the angle allows it.
+    pub fn bend (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack, refraction_ratio: f64, reflect_ray: bool) -> Ray {
         let unit_direction = unit_vector(&r_in.direction);
-        let cos_theta = dot(&-unit_direction, &rec.normal).min(1.0);
+        let cos_theta = dot(&-unit_direction, &rec.shading_normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
-        let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction: Vec3;
-
-        if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > random_f64_01() {
-            direction = reflect(&unit_direction, &rec.normal);
+        let direction = if reflect_ray || refraction_ratio * sin_theta > 1.0 {
+            reflect(&unit_direction, &rec.shading_normal)
         } else {
-            direction = refract(&unit_direction, &rec.normal, refraction_ratio);
-        }
+            if rec.front_face {
+                media.enter(&self.inside);
+            } else {
+                media.leave(&self.inside);
+            }
+            refract(&unit_direction, &rec.shading_normal, refraction_ratio)
+        };
+        Ray::new(&rec.p, &direction).with_time(r_in.time)
+    }
 
-        Some((
-            Color::new(1.0, 1.0, 1.0),
-            Ray::new(&rec.p, &direction)
-        ))
+    // Refracts or reflects at wavelength `lambda`, or with the RGB indices without one.
+    // Also tells whether the direction depended on the wavelength.
+    fn scatter_at (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack, lambda: Option<f64>) -> (Ray, bool) {
+        let (incident_ior, far_ior, dispersive) = self.indices(rec, media, lambda);
+        let refraction_ratio = incident_ior / far_ior;
+        let cos_theta = dot(&-unit_vector(&r_in.direction), &rec.shading_normal).min(1.0);
+        let reflect_ray = Self::reflectance(cos_theta, refraction_ratio) > random_f64_01();
+        (self.bend(r_in, rec, media, refraction_ratio, reflect_ray), dispersive)
     }
 }
 
+impl Material for Dielectric {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.scatter_in(r_in, rec, &mut MediumStack::default())
+    }
+
+    fn scatter_in (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack) -> Option<(Color, Ray)> {
+        let (scattered, _) = self.scatter_at(r_in, rec, media, None);
+        Some((Color::new(1.0, 1.0, 1.0), scattered))
+    }
+
+    // Dispersive glass bends every wavelength differently, so only the hero's direction is followed.
+    fn scatter_spectral (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambdas: &mut Wavelengths,
+    ) -> Option<(Spectrum, Ray)> {
+        let (scattered, dispersive) = self.scatter_at(r_in, rec, media, Some(lambdas.hero()));
+        if dispersive {
+            lambdas.terminate_secondary();
+        }
+        Some((Spectrum::constant(1.0), scattered))
+    }
+
+    fn is_specular (&self) -> bool {
+        true
+    }
+}
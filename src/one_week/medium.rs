@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use crate::common::vec3::Color;
+use crate::one_week::material::Material;
+use crate::one_week::random_f64_01;
+use crate::one_week::volume::HenyeyGreenstein;
+
+// Index of refraction as a function of wavelength, in micrometres as in glass catalogues.
+pub enum Dispersion {
+    // n = a + b / λ²
+    Cauchy { a: f64, b: f64 },
+    // n² = 1 + Σ b λ² / (λ² - c)
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    // `lambda` in nanometres.
+    pub fn ior (&self, lambda: f64) -> f64 {
+        let l2 = (lambda / 1000.0) * (lambda / 1000.0);
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                (1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>()).sqrt()
+            }
+        }
+    }
+}
+
+// Homogeneous interior of a transmissive object: its index of refraction and the
+// absorption and scattering coefficients per unit length of light crossing it.
+// Dispersive media use `ior`, their index at the sodium d-line, outside spectral
+// rendering.
+pub struct Medium {
+    pub ior: f64,
+    pub absorption: Color,
+    pub scattering: Color,
+    // phase function at scattering events, with an albedo of one
+    pub phase: Arc<dyn Material>,
+    pub dispersion: Option<Dispersion>,
+}
+
+impl Medium {
+    pub fn new (ior: f64, absorption: Color) -> Self {
+        Self {
+            ior,
+            absorption,
+            scattering: Color::default(),
+            phase: Arc::new(HenyeyGreenstein { albedo: Color::new(1.0, 1.0, 1.0), g: 0.0 }),
+            dispersion: None,
+        }
+    }
+
+    pub fn dispersive (dispersion: Dispersion, absorption: Color) -> Self {
+        let ior = dispersion.ior(587.6);
+        Self { dispersion: Some(dispersion), ..Self::new(ior, absorption) }
+    }
+
+    // Scattering interior from the single-scattering `albedo` and the mean free path
+    // per channel, with Henyey-Greenstein anisotropy `g`.
+    pub fn participating (ior: f64, albedo: Color, mean_free_path: Color, g: f64) -> Self {
+        let extinction = Color::new(1.0 / mean_free_path.x, 1.0 / mean_free_path.y, 1.0 / mean_free_path.z);
+        let scattering = albedo * extinction;
+        Self {
+            scattering,
+            phase: Arc::new(HenyeyGreenstein { albedo: Color::new(1.0, 1.0, 1.0), g }),
+            ..Self::new(ior, extinction - scattering)
+        }
+    }
+
+    // Index at `lambda` nanometres, or the RGB index without a wavelength.
+    pub fn ior_at (&self, lambda: Option<f64>) -> f64 {
+        match (&self.dispersion, lambda) {
+            (Some(dispersion), Some(lambda)) => dispersion.ior(lambda),
+            _ => self.ior,
+        }
+    }
+}
+
+// Media a path is inside of, innermost last. An empty stack is vacuum. It also keeps
+// the colour channel the path samples scattering distances by, and the ratios of each
+// channel's path density to that one's.
+#[derive(Clone)]
+pub struct MediumStack {
+    media: Vec<Arc<Medium>>,
+    channel: Option<usize>,
+    ratios: Color,
+}
+
+impl Default for MediumStack {
+    fn default () -> Self {
+        Self {
+            media: Vec::new(),
+            channel: None,
+            ratios: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl MediumStack {
+    pub fn current (&self) -> Option<&Arc<Medium>> {
+        self.media.last()
+    }
+
+    // Medium on the far side when leaving `medium`.
+    pub fn outside_of (&self, medium: &Arc<Medium>) -> Option<&Arc<Medium>> {
+        self.media.iter().rev().find(|m| !Arc::ptr_eq(m, medium))
+    }
+
+    pub fn enter (&mut self, medium: &Arc<Medium>) {
+        self.media.push(medium.clone());
+    }
+
+    pub fn leave (&mut self, medium: &Arc<Medium>) {
+        if let Some(index) = self.media.iter().rposition(|m| Arc::ptr_eq(m, medium)) {
+            self.media.remove(index);
+        }
+    }
+
+    // Samples whether a path with `distance` to go to the next surface scatters in the
+    // current medium first. Distances follow one colour channel per path and are
+    // weighted by the mean of every channel's density over the whole path (spectral
+    // MIS), so each channel may have its own mean free path without the weights
+    // blowing up along long walks. Purely absorbing media just apply Beer-Lambert.
+    pub fn sample (&mut self, distance: f64) -> MediumEvent {
+        let medium = match self.current() {
+            Some(medium) => medium.clone(),
+            None => return MediumEvent::Pass { weight: Color::new(1.0, 1.0, 1.0) },
+        };
+        let extinction = medium.absorption + medium.scattering;
+        let transmittance = |d: f64| Color::new(
+            (-extinction.x * d).exp(),
+            (-extinction.y * d).exp(),
+            (-extinction.z * d).exp(),
+        );
+        if medium.scattering.near_zero() {
+            return MediumEvent::Pass { weight: transmittance(distance) };
+        }
+
+        let channel = *self.channel.get_or_insert_with(|| ((random_f64_01() * 3.0) as usize).min(2));
+        let t = -(1.0 - random_f64_01()).ln() / extinction[channel];
+        let (f, pdf) = if t < distance {
+            let tr = transmittance(t);
+            (tr * medium.scattering, extinction * tr)
+        } else {
+            let tr = transmittance(distance);
+            (tr, tr)
+        };
+
+        let mean = |c: Color| (c.x + c.y + c.z) / 3.0;
+        let before = mean(self.ratios);
+        self.ratios *= pdf / pdf[channel];
+        let weight = f / pdf[channel] * (before / mean(self.ratios));
+        if t < distance {
+            MediumEvent::Scatter { distance: t, weight, phase: medium.phase.clone() }
+        } else {
+            MediumEvent::Pass { weight }
+        }
+    }
+}
+
+pub enum MediumEvent {
+    // The path scatters `distance` along the ray, by `phase`.
+    Scatter { distance: f64, weight: Color, phase: Arc<dyn Material> },
+    // The path reaches the next surface.
+    Pass { weight: Color },
+}
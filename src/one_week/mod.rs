@@ -1,10 +1,25 @@
 use std::f64::consts::PI;
-use rand::Rng;
+
+use crate::common::sampler;
 
 pub mod hittable;
 pub mod material;
 pub mod hittable_list;
 pub mod sphere;
+pub mod aabb;
+pub(crate) mod bvh;
+pub mod light;
+pub mod scene;
+pub(crate) mod perlin;
+pub mod volume;
+pub mod medium;
+pub mod texture;
+pub mod thin_film;
+pub mod subsurface;
+pub mod layered;
+pub mod normal_map;
+pub mod cutout;
+pub mod transform;
 
 pub fn degrees_to_radians (degrees: f64) -> f64 {
     degrees * PI / 180.0
@@ -21,8 +36,7 @@ pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
 }
 
 pub fn random_f64_01 () -> f64 {
-    let mut rng = rand::thread_rng();
-    rng.gen()
+    sampler::next_f64()
 }
 
 pub fn random_f64 (min: f64, max: f64) -> f64 {
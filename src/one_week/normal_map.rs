@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use crate::common::ray::Ray;
+use crate::common::spectrum::{Spectrum, Wavelengths};
+use crate::common::vec3::{Color, Vec3};
+use crate::common::vec3_opts::{cross, dot, unit_vector};
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::material::Material;
+use crate::one_week::medium::MediumStack;
+use crate::one_week::texture::Texture;
+
+// Step in u and v for differencing a height map.
+const BUMP_DELTA: f64 = 0.0005;
+
+// How the shading normal is tilted.
+pub enum Perturbation {
+    // Tangent-space normal map, each channel mapped from [0, 1] to [-1, 1]: x along
+    // dp/du, y across it on the surface and z along the normal.
+    NormalMap(Arc<dyn Texture>),
+    // Height map displacing the surface along its normal by `scale` times the texture.
+    Bump { height: Arc<dyn Texture>, scale: f64 },
+}
+
+// `base` shaded with a detailed normal, the geometry staying as it is. Surfaces
+// without tangents, such as scattering points in media, are left alone.
+pub struct NormalMapped {
+    pub base: Arc<dyn Material>,
+    pub perturbation: Perturbation,
+}
+
+impl NormalMapped {
+    pub fn normal_map (base: Arc<dyn Material>, map: Arc<dyn Texture>) -> Self {
+        Self { base, perturbation: Perturbation::NormalMap(map) }
+    }
+
+    pub fn bump (base: Arc<dyn Material>, height: Arc<dyn Texture>, scale: f64) -> Self {
+        Self { base, perturbation: Perturbation::Bump { height, scale } }
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.base.scatter(r_in, rec)
+    }
+
+    fn eval (&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        self.base.eval(r_in, rec, direction)
+    }
+
+    fn pdf (&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.base.pdf(r_in, rec, direction)
+    }
+
+    fn scatter_in (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack) -> Option<(Color, Ray)> {
+        self.base.scatter_in(r_in, rec, media)
+    }
+
+    fn scatter_spectral (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambdas: &mut Wavelengths,
+    ) -> Option<(Spectrum, Ray)> {
+        self.base.scatter_spectral(r_in, rec, media, lambdas)
+    }
+
+    fn transmission (&self, distance: f64) -> Color {
+        self.base.transmission(distance)
+    }
+
+    fn opacity (&self, rec: &HitRecord) -> f64 {
+        self.base.opacity(rec)
+    }
+
+    fn perturb (&self, rec: &mut HitRecord) {
+        if rec.dpdu.near_zero() || rec.dpdv.near_zero() {
+            return;
+        }
+        let outward = if rec.front_face { rec.shading_normal } else { -rec.shading_normal };
+
+        let normal = match &self.perturbation {
+            Perturbation::NormalMap(map) => {
+                let c = map.value(rec.u, rec.v, &rec.p) * 2.0 - Color::new(1.0, 1.0, 1.0);
+                let tangent = unit_vector(&(rec.dpdu - outward * dot(&rec.dpdu, &outward)));
+                let bitangent = cross(&outward, &tangent);
+                unit_vector(&(tangent * c.x + bitangent * c.y + outward * c.z))
+            }
+            Perturbation::Bump { height, scale } => {
+                let h = |u: f64, v: f64, offset: Vec3| height.value(u, v, &(rec.p + offset)).x * scale;
+                let displacement = h(rec.u, rec.v, Vec3::default());
+                let du = (h(rec.u + BUMP_DELTA, rec.v, rec.dpdu * BUMP_DELTA) - displacement) / BUMP_DELTA;
+                let dv = (h(rec.u, rec.v + BUMP_DELTA, rec.dpdv * BUMP_DELTA) - displacement) / BUMP_DELTA;
+                rec.dpdu += outward * du;
+                rec.dpdv += outward * dv;
+                let normal = unit_vector(&cross(&rec.dpdu, &rec.dpdv));
+                if dot(&normal, &outward) < 0.0 { -normal } else { normal }
+            }
+        };
+
+        rec.shading_normal = if rec.front_face { normal } else { -normal };
+        self.base.perturb(rec);
+    }
+
+    fn is_specular (&self) -> bool {
+        self.base.is_specular()
+    }
+
+    fn scatter_lobe (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray, bool)> {
+        self.base.scatter_lobe(r_in, rec)
+    }
+}
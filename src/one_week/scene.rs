@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::common::ray::Ray;
+use crate::common::stats;
+use crate::common::vec3::{Color, Point3, Vec3};
+use crate::common::vec3_opts::unit_vector;
+use crate::one_week::aabb::Aabb;
+use crate::one_week::bvh::BvhNode;
+use crate::one_week::hittable::{HitRecord, Hittable};
+use crate::one_week::hittable_list::HittableList;
+use crate::one_week::light::LightList;
+use crate::one_week::material::Material;
+use crate::one_week::transform::Animated;
+
+pub enum Background {
+    Sky,
+    Solid(Color),
+}
+
+impl Background {
+    pub fn radiance (&self, direction: &Vec3) -> Color {
+        match self {
+            Background::Sky => {
+                let unit_direction = unit_vector(direction);
+                let t = unit_direction.y * 0.5 + 1.0;
+                Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+            }
+            Background::Solid(color) => *color,
+        }
+    }
+}
+
+// `world` counts the rays traced into it, `uncounted` is the same objects without
+// that, for shadow rays that look for any hit.
+pub struct Scene {
+    pub world: Arc<dyn Hittable>,
+    pub lights: LightList,
+    pub background: Background,
+    uncounted: Arc<dyn Hittable>,
+    // what `world` is built from, kept to rebuild it as animated objects move
+    objects: Vec<Arc<dyn Hittable>>,
+    animated: Vec<Animated>,
+    // material ids by the address of the material
+    material_ids: HashMap<usize, usize>,
+}
+
+// Stamps its number on every hit of a top level object.
+struct Numbered {
+    id: usize,
+    object: Arc<dyn Hittable>,
+}
+
+impl Hittable for Numbered {
+    fn hit (&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rec = self.object.hit(r, t_min, t_max)?;
+        rec.object_id = self.id;
+        Some(rec)
+    }
+
+    fn bounding_box (&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        self.object.transmittance(r, t_min, t_max)
+    }
+}
+
+// Counts closest hit queries as rays and transmittance queries as shadow rays.
+struct Counted {
+    world: Arc<dyn Hittable>,
+}
+
+impl Hittable for Counted {
+    fn hit (&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        stats::ray();
+        self.world.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box (&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.world.bounding_box(time0, time1)
+    }
+
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        stats::shadow_ray();
+        self.world.transmittance(r, t_min, t_max)
+    }
+}
+
+fn material_key (material: &Arc<dyn Material>) -> usize {
+    Arc::as_ptr(material) as *const () as usize
+}
+
+// Distinct materials numbered from 1 in the order the objects use them, animated
+// objects last, like the object ids.
+fn number_materials (objects: &[Arc<dyn Hittable>], animated: &[Animated]) -> HashMap<usize, usize> {
+    let mut ids = HashMap::new();
+    let mut add = |material: &Arc<dyn Material>| {
+        let next = ids.len() + 1;
+        ids.entry(material_key(material)).or_insert(next);
+    };
+    for object in objects.iter().chain(animated.iter().map(|animated| &animated.object)) {
+        object.materials(&mut add);
+    }
+    ids
+}
+
+// Animated objects come after the others, so ids stay put from frame to frame.
+fn build_world (objects: Vec<Arc<dyn Hittable>>) -> Arc<dyn Hittable> {
+    let mut objects: Vec<Arc<dyn Hittable>> = objects
+        .into_iter()
+        .enumerate()
+        .map(|(i, object)| Arc::new(Numbered { id: i + 1, object }) as Arc<dyn Hittable>)
+        .collect();
+    if objects.is_empty() {
+        Arc::new(HittableList { objects })
+    } else {
+        Arc::new(BvhNode::new(&mut objects, 0.0, 1.0))
+    }
+}
+
+impl Scene {
+    pub fn new (objects: HittableList, lights: LightList, background: Background) -> Self {
+        let world = build_world(objects.objects.clone());
+        Self {
+            world: Arc::new(Counted { world: Arc::clone(&world) }),
+            uncounted: world,
+            lights,
+            background,
+            material_ids: number_materials(&objects.objects, &[]),
+            objects: objects.objects,
+            animated: Vec::new(),
+        }
+    }
+
+    // Adds objects that move, placed as they are at time 0.
+    pub fn with_animated (mut self, animated: Vec<Animated>) -> Self {
+        self.animated = animated;
+        self.material_ids = number_materials(&self.objects, &self.animated);
+        self.place_animated(0.0, 0.0);
+        self
+    }
+
+    pub fn is_animated (&self) -> bool {
+        self.animated.iter().any(|object| {
+            !object.translation.is_constant() || !object.rotation.is_constant() || !object.scale.is_constant()
+        })
+    }
+
+    // Moves the animated objects to `time` and rebuilds the BVH around them. With a
+    // `shutter` open for that many seconds they go on moving with `Ray::time`. Scenes
+    // where nothing moves keep theirs.
+    pub fn set_time (&mut self, time: f64, shutter: f64) {
+        if self.is_animated() {
+            self.place_animated(time, shutter);
+        }
+    }
+
+    fn place_animated (&mut self, time: f64, shutter: f64) {
+        let mut objects = self.objects.clone();
+        objects.extend(self.animated.iter().map(|object| {
+            if shutter > 0.0 {
+                Arc::new(object.during(time, shutter)) as Arc<dyn Hittable>
+            } else {
+                Arc::new(object.at(time)) as Arc<dyn Hittable>
+            }
+        }));
+        self.uncounted = build_world(objects);
+        self.world = Arc::new(Counted { world: Arc::clone(&self.uncounted) });
+    }
+
+    // Id of `material` among the scene's materials, 0 for one no object was built with,
+    // such as the phase function of a medium inside a dielectric.
+    pub fn material_id (&self, material: &Arc<dyn Material>) -> usize {
+        self.material_ids.get(&material_key(material)).copied().unwrap_or(0)
+    }
+
+    // Bounding sphere of the world.
+    pub fn bounds (&self) -> (Point3, f64) {
+        match self.world.bounding_box(0.0, 1.0) {
+            Some(bbox) => (bbox.centroid(), (bbox.maximum - bbox.minimum).length() / 2.0),
+            None => (Point3::default(), 0.0),
+        }
+    }
+
+    // Whether nothing is between the two points at `time`, see `Ray::time`.
+    pub fn unoccluded (&self, from: &Point3, to: &Point3, time: f64) -> bool {
+        let d = *to - *from;
+        let distance = d.length();
+        let shadow_ray = Ray::new(from, &(d / distance)).with_time(time);
+        stats::shadow_ray();
+        self.uncounted.hit(&shadow_ray, 0.001, distance - 0.001).is_none()
+    }
+
+    // Radiance reflected towards r_in from every light, each attenuated along a shadow ray.
+    pub fn sample_lights (&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        let mut direct = Color::default();
+        for light in self.lights.lights.iter() {
+            if let Some(sample) = light.sample_li(&rec.p) {
+                let f = rec.material.eval(r_in, rec, &sample.wi);
+                if f.near_zero() {
+                    continue;
+                }
+                let shadow_ray = Ray::new(&rec.p, &sample.wi).with_time(r_in.time);
+                direct += f * sample.radiance * self.world.transmittance(&shadow_ray, 0.001, sample.distance);
+            }
+        }
+        direct
+    }
+}
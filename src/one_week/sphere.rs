@@ -1,10 +1,13 @@
-use std::ops::Deref;
+use std::f64::consts::PI;
 use std::sync::Arc;
 
-use crate::common::vec3::{Point3, Vec3};
+use crate::common::vec3::{Color, Point3, Vec3};
 use crate::common::vec3_opts::dot;
 use crate::common::ray::Ray;
+use crate::common::onb::Onb;
+use crate::common::stats;
 
+use crate::one_week::aabb::Aabb;
 use crate::one_week::material::Material;
 use crate::one_week::hittable::{HitRecord, Hittable};
 
@@ -22,10 +25,59 @@ impl Sphere {
             material: Arc::from(material)
         }
     }
+
+    // p is a point on the unit sphere; u, v are both in [0, 1].
+    fn get_sphere_uv (p: &Point3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    fn record (&self, r: &Ray, root: f64) -> HitRecord {
+        let mut rec = HitRecord {
+            t: root,
+            p: r.at(root),
+            u: 0.0,
+            v: 0.0,
+            normal: Vec3::default(),
+            shading_normal: Vec3::default(),
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            front_face: false,
+            material: self.material.clone(),
+            object_id: 0,
+        };
+
+        let outward_normal = (rec.p - self.center) / self.radius;
+        rec.set_face_normal(r, &outward_normal);
+        let (u, v) = Self::get_sphere_uv(&outward_normal);
+        rec.u = u;
+        rec.v = v;
+        let (dpdu, dpdv) = self.get_sphere_tangents(&outward_normal);
+        rec.dpdu = dpdu;
+        rec.dpdv = dpdv;
+        self.material.perturb(&mut rec);
+        rec
+    }
+
+    // Derivatives of the hit point along u and v at p on the unit sphere. Their cross
+    // product points outwards. The poles pinch the parameterisation, so any frame does there.
+    fn get_sphere_tangents (&self, p: &Point3) -> (Vec3, Vec3) {
+        let sin_theta = (p.x * p.x + p.z * p.z).sqrt();
+        if sin_theta < 1e-8 {
+            let frame = Onb::build_from_w(p);
+            return (frame.v * self.radius, frame.u * self.radius);
+        }
+        (
+            Vec3::new(p.z, 0.0, -p.x) * (2.0 * PI * self.radius),
+            Vec3::new(-p.x * p.y / sin_theta, sin_theta, -p.y * p.z / sin_theta) * (PI * self.radius),
+        )
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        stats::intersection_test();
         let oc = r.origin - self.center;
         let a = r.direction.length_squared();
         let half_b = dot(&oc, &r.direction);
@@ -37,26 +89,63 @@ impl Hittable for Sphere {
         }
         let sqrtd = discriminant.sqrt();
 
-        // Find the nearest root that lies in the acceptable range.
-        let mut root = (-half_b - sqrtd) / a;
-        if root < t_min || t_max < root {
-            root = (-half_b + sqrtd) / a;
+        // Nearest root in the acceptable range whose surface isn't cut away.
+        for root in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
             if root < t_min || t_max < root {
-                return None;
+                continue;
+            }
+            let rec = self.record(r, root);
+            if !rec.cut_out() {
+                return Some(rec);
             }
         }
+        None
+    }
 
-        let mut rec = HitRecord {
-            t: root,
-            p: r.at(root),
-            normal: Vec3::default(),
-            front_face: false,
-            material: self.material.clone()
-        };
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - radius, self.center + radius))
+    }
 
-        let outward_normal = (rec.p - self.center) / self.radius;
-        rec.set_face_normal(r, &outward_normal);
+    fn materials (&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.material);
+    }
+
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        let oc = r.origin - self.center;
+        let a = r.direction.length_squared();
+        let half_b = dot(&oc, &r.direction);
+        let c = oc.length_squared() - self.radius * self.radius;
 
-        Some(rec)
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+        let sqrtd = discriminant.sqrt();
+
+        // Part of [t_min, t_max] inside the sphere.
+        let (near, far) = ((-half_b - sqrtd) / a, (-half_b + sqrtd) / a);
+        let enter = near.max(t_min);
+        let exit = far.min(t_max);
+        if exit <= enter {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+        let inside = self.material.transmission((exit - enter) * a.sqrt());
+        if !inside.near_zero() {
+            return inside;
+        }
+
+        // Opaque surfaces only let light through their cutouts, expected rather than sampled.
+        let mut clear = 1.0;
+        for root in [near, far] {
+            if t_min <= root && root <= t_max {
+                let rec = self.record(r, root);
+                clear *= 1.0 - self.material.opacity(&rec);
+                if clear <= 0.0 {
+                    break;
+                }
+            }
+        }
+        Color::new(clear, clear, clear)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use crate::common::ray::Ray;
+use crate::common::spectrum::{Spectrum, Wavelengths};
+use crate::common::vec3::Color;
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::material::{Dielectric, Material};
+use crate::one_week::medium::{Medium, MediumStack};
+
+// Translucent material such as skin, marble or wax: a smooth dielectric boundary around
+// a scattering interior. Paths that refract in random walk through the medium stack
+// until they leave again, so only integrators carrying a `MediumStack` see the
+// scattering, the others treat the object as clear glass. Shadow rays cross the
+// boundary without refracting and fade with the unscattered transmittance, which
+// lets lights reach points under the surface.
+pub struct Subsurface {
+    interface: Dielectric,
+}
+
+impl Subsurface {
+    // `albedo` is the single-scattering albedo, `mean_free_path` the average distance
+    // between interactions in scene units, both per colour channel.
+    pub fn new (albedo: Color, mean_free_path: Color, ior: f64, g: f64) -> Self {
+        Self {
+            interface: Dielectric::with_media(Arc::new(Medium::participating(ior, albedo, mean_free_path, g)), None),
+        }
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.interface.scatter(r_in, rec)
+    }
+
+    fn scatter_in (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack) -> Option<(Color, Ray)> {
+        self.interface.scatter_in(r_in, rec, media)
+    }
+
+    fn scatter_spectral (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambdas: &mut Wavelengths,
+    ) -> Option<(Spectrum, Ray)> {
+        self.interface.scatter_spectral(r_in, rec, media, lambdas)
+    }
+
+    fn transmission (&self, distance: f64) -> Color {
+        let medium = &self.interface.inside;
+        let extinction = medium.absorption + medium.scattering;
+        Color::new(
+            (-extinction.x * distance).exp(),
+            (-extinction.y * distance).exp(),
+            (-extinction.z * distance).exp(),
+        )
+    }
+
+    fn is_specular (&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,89 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::common::image::Image;
+use crate::common::vec3::{Color, Point3};
+use crate::one_week::perlin::Perlin;
+
+// Spatially varying material parameter, looked up by surface (u, v) and hit point.
+// Scalar parameters read the first channel.
+pub trait Texture: Send + Sync {
+    fn value (&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+pub struct SolidColor {
+    pub color: Color,
+}
+
+impl SolidColor {
+    pub fn new (color: Color) -> Self {
+        Self { color }
+    }
+
+    pub fn scalar (value: f64) -> Self {
+        Self::new(Color::new(value, value, value))
+    }
+}
+
+impl Texture for SolidColor {
+    fn value (&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.color
+    }
+}
+
+// Marble-like bands of turbulent Perlin noise blending from `low` to `high`.
+pub struct NoiseTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub low: Color,
+    pub high: Color,
+}
+
+impl Texture for NoiseTexture {
+    fn value (&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let t = 0.5 * (1.0 + (self.scale * p.z + 10.0 * self.noise.turb(p, 7)).sin());
+        self.low * (1.0 - t) + self.high * t
+    }
+}
+
+// Picture wrapped over (u, v), v running bottom to top, filtered bilinearly and tiled
+// in both directions. Values are kept as stored, without decoding gamma, which is
+// what normal and height maps want.
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    // `pixels` row by row from the top, each channel in [0, 1].
+    pub fn new (width: usize, height: usize, pixels: Vec<Color>) -> std::io::Result<Self> {
+        if width == 0 || height == 0 || width.checked_mul(height) != Some(pixels.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "image texture: pixel count doesn't match the size"));
+        }
+        Ok(Self { image: Image::from_pixels(width, height, pixels) })
+    }
+
+    // Any image `Image::load` reads, its values taken as they are stored.
+    pub fn load (path: &Path) -> std::io::Result<Self> {
+        Ok(Self { image: Image::load(path)? })
+    }
+
+    fn texel (&self, i: i64, j: i64) -> Color {
+        let i = i.rem_euclid(self.image.width as i64) as usize;
+        let j = j.rem_euclid(self.image.height as i64) as usize;
+        self.image.get(i, j)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value (&self, u: f64, v: f64, _p: &Point3) -> Color {
+        let x = u * self.image.width as f64 - 0.5;
+        let y = (1.0 - v) * self.image.height as f64 - 0.5;
+        let (i, j) = (x.floor(), y.floor());
+        let (fx, fy) = (x - i, y - j);
+        let (i, j) = (i as i64, j as i64);
+        self.texel(i, j) * ((1.0 - fx) * (1.0 - fy))
+            + self.texel(i + 1, j) * (fx * (1.0 - fy))
+            + self.texel(i, j + 1) * ((1.0 - fx) * fy)
+            + self.texel(i + 1, j + 1) * (fx * fy)
+    }
+}
@@ -0,0 +1,188 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::common::ray::Ray;
+use crate::common::spectrum::{Spectrum, Wavelengths, WAVELENGTHS};
+use crate::common::vec3::Color;
+use crate::common::vec3_opts::{dot, unit_vector};
+use crate::one_week::hittable::HitRecord;
+use crate::one_week::material::{Dielectric, Material, Metal};
+use crate::one_week::medium::MediumStack;
+use crate::one_week::texture::Texture;
+use crate::one_week::{clamp, random_f64_01};
+
+// What the film is deposited on.
+pub enum Substrate {
+    Dielectric(Dielectric),
+    Metal(Metal),
+}
+
+// A film `thickness` nanometres thick with index `ior` on top of a substrate, as in
+// soap bubbles, oil slicks and lens coatings. Light reflected off its two sides
+// interferes, so reflectance depends on wavelength, thickness and angle. The RGB
+// pipeline evaluates it at one wavelength per channel, spectral rendering at the
+// path's wavelengths.
+pub struct ThinFilm {
+    pub substrate: Substrate,
+    pub thickness: Arc<dyn Texture>,
+    pub ior: Arc<dyn Texture>,
+}
+
+// Wavelengths standing in for the red, green and blue channels.
+const RGB_WAVELENGTHS: [f64; 3] = [630.0, 532.0, 465.0];
+
+// Medium behind the film.
+enum Back {
+    Dielectric(f64),
+    // amplitude reflectance, the conductor flips its phase
+    Conductor(f64),
+}
+
+// Airy reflectance of a film of index `n2` between `n1` and `back`, averaged over
+// both polarisations, for light arriving at `cos_i` from the normal.
+fn film_reflectance (cos_i: f64, n1: f64, n2: f64, thickness: f64, lambda: f64, back: Back) -> f64 {
+    let sin2_i = (1.0 - cos_i * cos_i).max(0.0);
+    let sin2_t = (n1 / n2) * (n1 / n2) * sin2_i;
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let phase = 4.0 * PI * n2 * thickness * cos_t / lambda;
+
+    let (r23s, r23p) = match back {
+        Back::Dielectric(n3) => {
+            let sin2_3 = (n1 / n3) * (n1 / n3) * sin2_i;
+            if sin2_3 >= 1.0 {
+                (1.0, 1.0)
+            } else {
+                let cos_3 = (1.0 - sin2_3).sqrt();
+                (
+                    (n2 * cos_t - n3 * cos_3) / (n2 * cos_t + n3 * cos_3),
+                    (n3 * cos_t - n2 * cos_3) / (n3 * cos_t + n2 * cos_3),
+                )
+            }
+        }
+        Back::Conductor(amplitude) => (-amplitude, -amplitude),
+    };
+    let r12s = (n1 * cos_i - n2 * cos_t) / (n1 * cos_i + n2 * cos_t);
+    let r12p = (n2 * cos_i - n1 * cos_t) / (n2 * cos_i + n1 * cos_t);
+
+    let airy = |r12: f64, r23: f64| {
+        let cross = 2.0 * r12 * r23 * phase.cos();
+        (r12 * r12 + r23 * r23 + cross) / (1.0 + r12 * r12 * r23 * r23 + cross)
+    };
+    clamp(0.5 * (airy(r12s, r23s) + airy(r12p, r23p)), 0.0, 1.0)
+}
+
+impl ThinFilm {
+    fn film (&self, rec: &HitRecord) -> (f64, f64) {
+        (
+            self.thickness.value(rec.u, rec.v, &rec.p).x.max(0.0),
+            self.ior.value(rec.u, rec.v, &rec.p).x,
+        )
+    }
+
+    // Film reflectance at each of `lambdas`, with `albedo` the metal's at the same wavelengths.
+    fn reflectances<const N: usize> (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &MediumStack,
+        lambdas: &[f64; N],
+        albedo: &[f64; N],
+    ) -> [f64; N] {
+        let (thickness, film_ior) = self.film(rec);
+        let cos_i = dot(&-unit_vector(&r_in.direction), &rec.shading_normal).abs().min(1.0);
+        let mut reflectance = [0.0; N];
+        for i in 0..N {
+            reflectance[i] = match &self.substrate {
+                Substrate::Dielectric(dielectric) => {
+                    let (n1, n3, _) = dielectric.indices(rec, media, Some(lambdas[i]));
+                    film_reflectance(cos_i, n1, film_ior, thickness, lambdas[i], Back::Dielectric(n3))
+                }
+                Substrate::Metal(_) => {
+                    let n1 = media.current().map_or(1.0, |medium| medium.ior_at(Some(lambdas[i])));
+                    film_reflectance(cos_i, n1, film_ior, thickness, lambdas[i], Back::Conductor(albedo[i].max(0.0).sqrt()))
+                }
+            };
+        }
+        reflectance
+    }
+
+    // Reflects with the chance `reflectance[0]` or refracts into a dielectric
+    // substrate, returning the per-wavelength weights of the choice.
+    fn choose<const N: usize> (
+        dielectric: &Dielectric,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambda: Option<f64>,
+        reflectance: &[f64; N],
+    ) -> ([f64; N], Ray, bool) {
+        let (n1, n3, dispersive) = dielectric.indices(rec, media, lambda);
+        let p = reflectance[0];
+        let reflect_ray = random_f64_01() < p;
+        let mut weights = [0.0; N];
+        for i in 0..N {
+            weights[i] = if reflect_ray {
+                reflectance[i] / p
+            } else {
+                (1.0 - reflectance[i]) / (1.0 - p)
+            };
+        }
+        (weights, dielectric.bend(r_in, rec, media, n1 / n3, reflect_ray), dispersive)
+    }
+}
+
+impl Material for ThinFilm {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.scatter_in(r_in, rec, &mut MediumStack::default())
+    }
+
+    fn scatter_in (&self, r_in: &Ray, rec: &HitRecord, media: &mut MediumStack) -> Option<(Color, Ray)> {
+        match &self.substrate {
+            Substrate::Dielectric(dielectric) => {
+                let r = self.reflectances(r_in, rec, media, &RGB_WAVELENGTHS, &[0.0; 3]);
+                // Choose by the mean over the channels so none of them is starved.
+                let mean = (r[0] + r[1] + r[2]) / 3.0;
+                let (w, scattered, _) = Self::choose(dielectric, r_in, rec, media, None, &[mean, r[0], r[1], r[2]]);
+                Some((Color::new(w[1], w[2], w[3]), scattered))
+            }
+            Substrate::Metal(metal) => {
+                let albedo = [metal.albedo.x, metal.albedo.y, metal.albedo.z];
+                let r = self.reflectances(r_in, rec, media, &RGB_WAVELENGTHS, &albedo);
+                let (_, scattered) = metal.scatter(r_in, rec)?;
+                Some((Color::new(r[0], r[1], r[2]), scattered))
+            }
+        }
+    }
+
+    fn scatter_spectral (
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &mut MediumStack,
+        lambdas: &mut Wavelengths,
+    ) -> Option<(Spectrum, Ray)> {
+        match &self.substrate {
+            Substrate::Dielectric(dielectric) => {
+                let r = self.reflectances(r_in, rec, media, &lambdas.lambda, &[0.0; WAVELENGTHS]);
+                let (values, scattered, dispersive) = Self::choose(dielectric, r_in, rec, media, Some(lambdas.hero()), &r);
+                if dispersive {
+                    lambdas.terminate_secondary();
+                }
+                Some((Spectrum { values }, scattered))
+            }
+            Substrate::Metal(metal) => {
+                let albedo = Spectrum::from_rgb(&metal.albedo, lambdas).values;
+                let values = self.reflectances(r_in, rec, media, &lambdas.lambda, &albedo);
+                let (_, scattered) = metal.scatter(r_in, rec)?;
+                Some((Spectrum { values }, scattered))
+            }
+        }
+    }
+
+    fn is_specular (&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use crate::common::keyframe::Track;
+use crate::common::ray::Ray;
+use crate::common::vec3::{Color, Point3, Vec3};
+use crate::common::vec3_opts::unit_vector;
+use crate::one_week::aabb::{surrounding_box, Aabb};
+use crate::one_week::degrees_to_radians;
+use crate::one_week::hittable::{HitRecord, Hittable};
+use crate::one_week::material::Material;
+
+// Uniform scale, then rotation about x, y and z in turn, then translation. Without
+// shear normals turn like any other direction.
+#[derive(Clone, Copy)]
+pub struct Transform {
+    translation: Vec3,
+    // rows of the rotation matrix
+    rotation: [Vec3; 3],
+    scale: f64,
+}
+
+fn rotate (rows: &[Vec3; 3], v: &Vec3) -> Vec3 {
+    Vec3::new(
+        rows[0].x * v.x + rows[0].y * v.y + rows[0].z * v.z,
+        rows[1].x * v.x + rows[1].y * v.y + rows[1].z * v.z,
+        rows[2].x * v.x + rows[2].y * v.y + rows[2].z * v.z,
+    )
+}
+
+fn transpose (rows: &[Vec3; 3]) -> [Vec3; 3] {
+    [
+        Vec3::new(rows[0].x, rows[1].x, rows[2].x),
+        Vec3::new(rows[0].y, rows[1].y, rows[2].y),
+        Vec3::new(rows[0].z, rows[1].z, rows[2].z),
+    ]
+}
+
+impl Transform {
+    // `rotation` holds the angles about x, y and z in degrees.
+    pub fn new (translation: Vec3, rotation: Vec3, scale: f64) -> Self {
+        let (sx, cx) = degrees_to_radians(rotation.x).sin_cos();
+        let (sy, cy) = degrees_to_radians(rotation.y).sin_cos();
+        let (sz, cz) = degrees_to_radians(rotation.z).sin_cos();
+        // Rz * Ry * Rx
+        let rows = [
+            Vec3::new(cz * cy, cz * sy * sx - sz * cx, cz * sy * cx + sz * sx),
+            Vec3::new(sz * cy, sz * sy * sx + cz * cx, sz * sy * cx - cz * sx),
+            Vec3::new(-sy, cy * sx, cy * cx),
+        ];
+        Self { translation, rotation: rows, scale }
+    }
+
+    pub fn point (&self, p: &Point3) -> Point3 {
+        rotate(&self.rotation, &(*p * self.scale)) + self.translation
+    }
+
+    pub fn vector (&self, v: &Vec3) -> Vec3 {
+        rotate(&self.rotation, &(*v * self.scale))
+    }
+
+    pub fn normal (&self, n: &Vec3) -> Vec3 {
+        rotate(&self.rotation, n)
+    }
+
+    // Box around `bbox` once transformed.
+    fn bounding_box (&self, bbox: &Aabb) -> Aabb {
+        let mut minimum = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut maximum = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for corner in 0..8 {
+            let p = self.point(&Point3::new(
+                if corner & 1 == 0 { bbox.minimum.x } else { bbox.maximum.x },
+                if corner & 2 == 0 { bbox.minimum.y } else { bbox.maximum.y },
+                if corner & 4 == 0 { bbox.minimum.z } else { bbox.maximum.z },
+            ));
+            minimum = Point3::new(minimum.x.min(p.x), minimum.y.min(p.y), minimum.z.min(p.z));
+            maximum = Point3::new(maximum.x.max(p.x), maximum.y.max(p.y), maximum.z.max(p.z));
+        }
+        Aabb::new(minimum, maximum)
+    }
+
+    fn hit (&self, object: &dyn Hittable, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rec = object.hit(&self.inverse_ray(r), t_min, t_max)?;
+        rec.p = self.point(&rec.p);
+        rec.normal = unit_vector(&self.normal(&rec.normal));
+        rec.shading_normal = unit_vector(&self.normal(&rec.shading_normal));
+        rec.dpdu = self.vector(&rec.dpdu);
+        rec.dpdv = self.vector(&rec.dpdv);
+        Some(rec)
+    }
+
+    // Object space ray, with the same parameter t along it as `r`.
+    fn inverse_ray (&self, r: &Ray) -> Ray {
+        let inverse = transpose(&self.rotation);
+        Ray {
+            origin: rotate(&inverse, &(r.origin - self.translation)) * (1.0 / self.scale),
+            direction: rotate(&inverse, &r.direction) * (1.0 / self.scale),
+            time: r.time,
+        }
+    }
+}
+
+// An object placed in the world by a transform.
+pub struct Instance {
+    pub object: Arc<dyn Hittable>,
+    pub transform: Transform,
+}
+
+impl Hittable for Instance {
+    fn hit (&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.transform.hit(self.object.as_ref(), r, t_min, t_max)
+    }
+
+    fn bounding_box (&self, time0: f64, time1: f64) -> Option<Aabb> {
+        Some(self.transform.bounding_box(&self.object.bounding_box(time0, time1)?))
+    }
+
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        self.object.transmittance(&self.transform.inverse_ray(r), t_min, t_max)
+    }
+
+    fn materials (&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.object.materials(f);
+    }
+}
+
+// An object moved by keyframed transform tracks, fixed into an `Instance` per frame,
+// or a `Moving` one while the shutter is open.
+#[derive(Clone)]
+pub struct Animated {
+    pub object: Arc<dyn Hittable>,
+    pub translation: Track<Vec3>,
+    // degrees about x, y and z
+    pub rotation: Track<Vec3>,
+    pub scale: Track<f64>,
+}
+
+impl Animated {
+    pub fn new (object: Arc<dyn Hittable>) -> Self {
+        Self {
+            object,
+            translation: Track::constant(Vec3::default()),
+            rotation: Track::constant(Vec3::default()),
+            scale: Track::constant(1.0),
+        }
+    }
+
+    pub fn at (&self, time: f64) -> Instance {
+        Instance {
+            object: Arc::clone(&self.object),
+            transform: self.transform(time),
+        }
+    }
+
+    // The object as it moves while the shutter is open from `time` for `shutter` seconds.
+    pub fn during (&self, time: f64, shutter: f64) -> Moving {
+        Moving { animated: self.clone(), time, shutter }
+    }
+
+    fn transform (&self, time: f64) -> Transform {
+        Transform::new(self.translation.at(time), self.rotation.at(time), self.scale.at(time))
+    }
+}
+
+// Steps the shutter is split into to bound a moving object. Keyframed moves are smooth
+// enough that the boxes at these times, and at the keys, cover the ones between.
+const MOTION_STEPS: usize = 16;
+
+// An animated object placed for each ray at the time it was traced, for motion blur.
+pub struct Moving {
+    animated: Animated,
+    // when the shutter opens, and for how many seconds
+    time: f64,
+    shutter: f64,
+}
+
+impl Moving {
+    fn transform (&self, r: &Ray) -> Transform {
+        self.animated.transform(self.time + r.time.clamp(0.0, self.shutter))
+    }
+
+    // Every time the moving object's box has to be taken at, the keys in between included.
+    fn sample_times (&self) -> Vec<f64> {
+        let (start, end) = (self.time, self.time + self.shutter);
+        let mut times: Vec<f64> = (0..=MOTION_STEPS).map(|i| start + self.shutter * i as f64 / MOTION_STEPS as f64).collect();
+        let animated = &self.animated;
+        times.extend(
+            animated
+                .translation
+                .times()
+                .chain(animated.rotation.times())
+                .chain(animated.scale.times())
+                .filter(|&time| time > start && time < end),
+        );
+        times
+    }
+}
+
+impl Hittable for Moving {
+    fn hit (&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.transform(r).hit(self.animated.object.as_ref(), r, t_min, t_max)
+    }
+
+    fn bounding_box (&self, time0: f64, time1: f64) -> Option<Aabb> {
+        let bbox = self.animated.object.bounding_box(time0, time1)?;
+        self.sample_times()
+            .into_iter()
+            .map(|time| self.animated.transform(time).bounding_box(&bbox))
+            .reduce(|a, b| surrounding_box(&a, &b))
+    }
+
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        let transform = self.transform(r);
+        self.animated.object.transmittance(&transform.inverse_ray(r), t_min, t_max)
+    }
+
+    fn materials (&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.animated.object.materials(f);
+    }
+}
@@ -0,0 +1,335 @@
+use std::f64::consts::PI;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::common::onb::Onb;
+use crate::common::ray::Ray;
+use crate::common::stats;
+use crate::common::vec3::{Color, Point3, Vec3};
+use crate::common::vec3_opts::{dot, unit_vector};
+use crate::one_week::aabb::Aabb;
+use crate::one_week::hittable::{HitRecord, Hittable};
+use crate::one_week::material::Material;
+use crate::one_week::perlin::Perlin;
+use crate::one_week::{clamp, random_f64_01};
+
+// Density of a participating medium, zero outside `bounds`.
+pub trait DensityField: Send + Sync {
+    fn density (&self, p: &Point3) -> f64;
+
+    // Upper bound of `density`, the majorant of delta and ratio tracking.
+    fn max_density (&self) -> f64;
+
+    fn bounds (&self) -> Aabb;
+}
+
+// Dense voxel grid stretched over `bounds`, sampled trilinearly between voxel centres.
+pub struct VoxelGrid {
+    nx: usize,
+    ny: usize,
+    nz: usize,
+    values: Vec<f32>,
+    max: f64,
+    bounds: Aabb,
+}
+
+fn invalid (msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("voxels: {}", msg))
+}
+
+// Largest grid a sparse file may ask for, a gigabyte of densities.
+const MAX_VOXELS: usize = 1 << 28;
+
+// nx * ny * nz, unless that overflows.
+fn voxel_count (nx: usize, ny: usize, nz: usize) -> std::io::Result<usize> {
+    nx.checked_mul(ny).and_then(|n| n.checked_mul(nz)).ok_or_else(|| invalid("grid too large"))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take (&mut self, n: usize) -> std::io::Result<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(invalid("unexpected end of file"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32 (&mut self) -> std::io::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn f32 (&mut self) -> std::io::Result<f32> {
+        let b = self.take(4)?;
+        Ok(f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+impl VoxelGrid {
+    pub fn new (nx: usize, ny: usize, nz: usize, values: Vec<f32>, bounds: Aabb) -> std::io::Result<Self> {
+        if nx == 0 || ny == 0 || nz == 0 || voxel_count(nx, ny, nz).ok() != Some(values.len()) {
+            return Err(invalid("grid size does not match its values"));
+        }
+        let max = values.iter().cloned().fold(0.0, f32::max) as f64;
+        Ok(Self { nx, ny, nz, values, max, bounds })
+    }
+
+    // Raw format: little-endian u32 nx, ny, nz, then nx * ny * nz f32 densities, x fastest.
+    pub fn load_raw (path: &Path, bounds: Aabb) -> std::io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut reader = Reader { bytes: &bytes };
+        let (nx, ny, nz) = (reader.u32()? as usize, reader.u32()? as usize, reader.u32()? as usize);
+        let count = voxel_count(nx, ny, nz)?;
+        if count.checked_mul(4) != Some(reader.bytes.len()) {
+            return Err(invalid("grid size does not match the file size"));
+        }
+        let mut values = Vec::with_capacity(count);
+        for _ in 0..count {
+            values.push(reader.f32()?);
+        }
+        Self::new(nx, ny, nz, values, bounds)
+    }
+
+    // Sparse format after NanoVDB's leaf nodes: only 8x8x8 blocks holding density are
+    // stored. Magic "SVDB", little-endian u32 nx, ny, nz and leaf count, then per leaf
+    // its u32 voxel origin and 512 f32 densities, x fastest. Voxels outside the grid
+    // are dropped, absent leaves are empty.
+    pub fn load_sparse (path: &Path, bounds: Aabb) -> std::io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut reader = Reader { bytes: &bytes };
+        if reader.take(4)? != b"SVDB" {
+            return Err(invalid("missing SVDB magic"));
+        }
+        let (nx, ny, nz) = (reader.u32()? as usize, reader.u32()? as usize, reader.u32()? as usize);
+        let leaves = reader.u32()?;
+        let count = voxel_count(nx, ny, nz)?;
+        if count > MAX_VOXELS {
+            return Err(invalid("grid too large"));
+        }
+
+        let mut values = vec![0.0; count];
+        for _ in 0..leaves {
+            let (x0, y0, z0) = (reader.u32()? as usize, reader.u32()? as usize, reader.u32()? as usize);
+            for k in 0..8 {
+                for j in 0..8 {
+                    for i in 0..8 {
+                        let value = reader.f32()?;
+                        let (x, y, z) = (x0 + i, y0 + j, z0 + k);
+                        if x < nx && y < ny && z < nz {
+                            values[(z * ny + y) * nx + x] = value;
+                        }
+                    }
+                }
+            }
+        }
+        Self::new(nx, ny, nz, values, bounds)
+    }
+
+    fn voxel (&self, x: usize, y: usize, z: usize) -> f64 {
+        self.values[(z * self.ny + y) * self.nx + x] as f64
+    }
+}
+
+// Trilinear weights along one axis: lower voxel, upper voxel, fraction of the upper.
+fn lerp_index (coord: f64, n: usize) -> (usize, usize, f64) {
+    let x = clamp(coord * n as f64 - 0.5, 0.0, (n - 1) as f64);
+    let i = (x as usize).min(n - 1);
+    (i, (i + 1).min(n - 1), x - i as f64)
+}
+
+impl DensityField for VoxelGrid {
+    fn density (&self, p: &Point3) -> f64 {
+        let extent = self.bounds.maximum - self.bounds.minimum;
+        let local = *p - self.bounds.minimum;
+        let (x0, x1, fx) = lerp_index(local.x / extent.x, self.nx);
+        let (y0, y1, fy) = lerp_index(local.y / extent.y, self.ny);
+        let (z0, z1, fz) = lerp_index(local.z / extent.z, self.nz);
+
+        let lerp = |a: f64, b: f64, t: f64| a * (1.0 - t) + b * t;
+        let plane = |z: usize| lerp(
+            lerp(self.voxel(x0, y0, z), self.voxel(x1, y0, z), fx),
+            lerp(self.voxel(x0, y1, z), self.voxel(x1, y1, z), fx),
+            fy,
+        );
+        lerp(plane(z0), plane(z1), fz).max(0.0)
+    }
+
+    fn max_density (&self) -> f64 {
+        self.max
+    }
+
+    fn bounds (&self) -> Aabb {
+        self.bounds
+    }
+}
+
+// Turbulent Perlin noise fading out towards the ellipsoid inscribed in `bounds`,
+// a puffy cloud with densities in [0, 1].
+pub struct NoiseDensity {
+    pub perlin: Perlin,
+    pub bounds: Aabb,
+    pub frequency: f64,
+    pub octaves: i32,
+}
+
+impl DensityField for NoiseDensity {
+    fn density (&self, p: &Point3) -> f64 {
+        let half = (self.bounds.maximum - self.bounds.minimum) * 0.5;
+        let d = *p - self.bounds.centroid();
+        let r = Vec3::new(d.x / half.x, d.y / half.y, d.z / half.z).length_squared();
+        if r >= 1.0 {
+            return 0.0;
+        }
+        clamp((1.0 - r) * 2.0 * self.perlin.turb(&(*p * self.frequency), self.octaves), 0.0, 1.0)
+    }
+
+    fn max_density (&self) -> f64 {
+        1.0
+    }
+
+    fn bounds (&self) -> Aabb {
+        self.bounds
+    }
+}
+
+// Henyey-Greenstein phase function, `g` > 0 scatters forward and `g` < 0 backward.
+pub struct HenyeyGreenstein {
+    pub albedo: Color,
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    // Density over solid angle of turning by an angle with cosine `cos_theta`.
+    fn phase (&self, cos_theta: f64) -> f64 {
+        let denom = 1.0 + self.g * self.g - 2.0 * self.g * cos_theta;
+        (1.0 - self.g * self.g) / (4.0 * PI * denom * denom.max(1e-12).sqrt())
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter (&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let g = self.g;
+        let u = random_f64_01();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            clamp((1.0 + g * g - s * s) / (2.0 * g), -1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_f64_01();
+        let direction = Onb::build_from_w(&unit_vector(&r_in.direction)).local(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            cos_theta,
+        );
+
+        Some((self.albedo, Ray::new(&rec.p, &direction).with_time(r_in.time)))
+    }
+
+    fn eval (&self, r_in: &Ray, _rec: &HitRecord, direction: &Vec3) -> Color {
+        self.albedo * self.phase(dot(&unit_vector(&r_in.direction), direction))
+    }
+
+    fn pdf (&self, r_in: &Ray, _rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.phase(dot(&unit_vector(&r_in.direction), direction))
+    }
+}
+
+// Participating medium with extinction `sigma_t * density(p)` and scattering albedo
+// `albedo`. Collisions are found by delta tracking, so `hit` returns a random point
+// along the ray (or nothing when it passes through), and shadow rays use ratio
+// tracking. Integrators that need a surface normal see one facing the ray.
+pub struct HeterogeneousMedium {
+    pub density: Arc<dyn DensityField>,
+    pub sigma_t: f64,
+    pub phase: Arc<dyn Material>,
+}
+
+impl HeterogeneousMedium {
+    pub fn new (density: Arc<dyn DensityField>, sigma_t: f64, albedo: Color, g: f64) -> Self {
+        Self {
+            density,
+            sigma_t,
+            phase: Arc::new(HenyeyGreenstein { albedo, g }),
+        }
+    }
+
+    // Free-flight distances against the majorant, in ray parameter units, from t0 until t1.
+    fn track<F: FnMut(f64) -> bool> (&self, r: &Ray, t0: f64, t1: f64, mut f: F) {
+        let majorant = self.sigma_t * self.density.max_density() * r.direction.length();
+        if majorant <= 0.0 {
+            return;
+        }
+        let mut t = t0;
+        loop {
+            t -= (1.0 - random_f64_01()).ln() / majorant;
+            if t >= t1 || !f(t) {
+                return;
+            }
+        }
+    }
+
+    // Probability that a tentative collision at `p` is real.
+    fn real_fraction (&self, p: &Point3) -> f64 {
+        self.density.density(p) / self.density.max_density()
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit (&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        stats::intersection_test();
+        let (t0, t1) = self.density.bounds().interval(r, t_min, t_max)?;
+        let mut collision = None;
+        self.track(r, t0, t1, |t| {
+            if random_f64_01() < self.real_fraction(&r.at(t)) {
+                collision = Some(t);
+                return false;
+            }
+            true
+        });
+
+        let t = collision?;
+        let normal = -unit_vector(&r.direction);
+        Some(HitRecord {
+            p: r.at(t),
+            normal,
+            shading_normal: normal,
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            material: self.phase.clone(),
+            t,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            object_id: 0,
+        })
+    }
+
+    fn bounding_box (&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(self.density.bounds())
+    }
+
+    fn materials (&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.phase);
+    }
+
+    fn transmittance (&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        stats::intersection_test();
+        let mut transmittance = 1.0;
+        if let Some((t0, t1)) = self.density.bounds().interval(r, t_min, t_max) {
+            self.track(r, t0, t1, |t| {
+                transmittance *= 1.0 - self.real_fraction(&r.at(t));
+                transmittance > 0.0
+            });
+        }
+        Color::new(transmittance, transmittance, transmittance)
+    }
+}
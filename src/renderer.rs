@@ -0,0 +1,253 @@
+use std::sync::{Arc, Mutex};
+use std::thread::spawn;
+use std::time::{Duration, Instant};
+
+use crate::common::camera::Camera;
+use crate::common::framebuffer::Framebuffer;
+use crate::common::image::Image;
+use crate::common::stats;
+use crate::common::vec3::Color;
+use crate::integrator::aov::{Aov, Aovs};
+use crate::integrator::{Integrator, Splat};
+use crate::one_week::random_f64_01;
+use crate::one_week::scene::Scene;
+
+// Renders scenes into framebuffers of linear colour, `width` by `height`, with
+// `samples_per_pixel` camera rays per pixel spread over `threads` threads, along with
+// any `aovs` asked for. A `crop` window limits the work to a rectangle of the frame.
+pub struct Renderer {
+    pub integrator: Arc<dyn Integrator>,
+    pub width: i32,
+    pub height: i32,
+    pub samples_per_pixel: i32,
+    pub threads: usize,
+    // report progress on stderr as it goes and the ray statistics at the end
+    pub progress: bool,
+    pub aovs: Vec<Aov>,
+    // x, y, width and height in pixels from the top left
+    pub crop: Option<(usize, usize, usize, usize)>,
+}
+
+impl Renderer {
+    pub fn new (integrator: Arc<dyn Integrator>, width: i32, height: i32) -> Self {
+        Self { integrator, width, height, samples_per_pixel: 100, threads: 4, progress: false, aovs: Vec::new(), crop: None }
+    }
+
+    pub fn with_samples (mut self, samples_per_pixel: i32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn with_threads (mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn with_progress (mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn with_aovs (mut self, aovs: Vec<Aov>) -> Self {
+        self.aovs = aovs;
+        self
+    }
+
+    // Panics unless the window lies within the frame.
+    pub fn with_crop (mut self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        assert!(x + width <= self.width as usize && y + height <= self.height as usize, "crop outside the image");
+        self.crop = Some((x, y, width, height));
+        self
+    }
+
+    // Only the crop window of images the size of the frame.
+    fn cropped (&self, image: Image) -> Image {
+        match self.crop {
+            Some((x, y, width, height)) => image.crop(x, y, width, height),
+            None => image,
+        }
+    }
+
+    // Mean radiance of every pixel with splats added and the camera's exposure applied,
+    // just the crop window's pixels if there is one.
+    pub fn render (&self, scene: &Arc<Scene>, camera: &Arc<Camera>) -> Image {
+        let mut image = self.cropped(self.render_framebuffer(scene, camera).resolve());
+        image.scale(camera.exposure_scale());
+        image
+    }
+
+    // The beauty image named "beauty", then each AOV by its name. Lighting passes are
+    // exposed like the beauty image, the others are kept as they are.
+    pub fn render_layers (&self, scene: &Arc<Scene>, camera: &Arc<Camera>) -> Vec<(String, Image)> {
+        self.resolve_layers(&self.render_framebuffer(scene, camera), camera)
+    }
+
+    // `render_layers` of a framebuffer already rendered.
+    pub fn resolve_layers (&self, framebuffer: &Framebuffer, camera: &Camera) -> Vec<(String, Image)> {
+        let mut beauty = self.cropped(framebuffer.resolve());
+        beauty.scale(camera.exposure_scale());
+        let mut layers = vec![(String::from("beauty"), beauty)];
+        for &aov in &self.aovs {
+            let mut image = self.cropped(framebuffer.resolve_channel(aov.name()).expect("channel for every AOV"));
+            if aov.is_lighting() {
+                image.scale(camera.exposure_scale());
+            }
+            layers.push((String::from(aov.name()), image));
+        }
+        layers
+    }
+
+    // Summed samples, splats and AOV channels before exposure, for the whole frame.
+    // With a crop window only pixels inside it are sampled, though splats still land
+    // anywhere and are scaled for the frame's size. Progressive integrators get one
+    // pass over the pixels per sample, the others a single pass.
+    pub fn render_framebuffer (&self, scene: &Arc<Scene>, camera: &Arc<Camera>) -> Framebuffer {
+        let (image_width, image_height) = (self.width, self.height);
+        let (passes, samples_per_pixel) = if self.integrator.progressive() {
+            (self.samples_per_pixel.max(0) as usize, 1)
+        } else {
+            (1, self.samples_per_pixel)
+        };
+
+        let (left, top, columns, rows) = self.crop.unwrap_or((0, 0, image_width as usize, image_height as usize));
+        let pixel_count = columns * rows;
+        let task_count = Arc::new(pixel_count * passes);
+        let start = Instant::now();
+        let last_report = Arc::new(Mutex::new(start));
+
+        let framebuffer = self.aovs.iter().fold(Framebuffer::new(image_width as usize, image_height as usize), |framebuffer, aov| {
+            framebuffer.with_channel(aov.name())
+        });
+        let framebuffer_mutex = Arc::new(Mutex::new(framebuffer));
+
+        for pass in 0..passes {
+            // Rays traced here, on the calling thread, belong to the render as well.
+            // Whatever this thread counted before does not.
+            stats::take();
+            self.integrator.begin_pass(scene, pass);
+            framebuffer_mutex.lock().unwrap().stats += stats::take();
+
+            // Prepare tasks
+            let mut tasks = vec![];
+            for y in top..top + rows {
+                for x in left..left + columns {
+                    tasks.push((x as i32, image_height - 1 - y as i32));
+                }
+            }
+            let tasks_mutex = Arc::new(Mutex::new(tasks));
+            let done_before = pass * pixel_count;
+            let mut thread_handlers = vec![];
+
+            for _ in 0..self.threads {
+                let world_arc = Arc::clone(scene);
+                let integrator_arc = Arc::clone(&self.integrator);
+                let camera_arc = Arc::clone(camera);
+                let framebuffer_arc = Arc::clone(&framebuffer_mutex);
+                let tasks_arc = Arc::clone(&tasks_mutex);
+                let task_count_arc = Arc::clone(&task_count);
+                let last_report_arc = Arc::clone(&last_report);
+                let progress = self.progress;
+                let aov_list = self.aovs.clone();
+
+                let render_handler = spawn(move || {
+                    let mut splats: Vec<Splat> = Vec::new();
+                    let mut aov_sums = vec![Color::default(); aov_list.len()];
+                    loop {
+                        let (task, remaining) = {
+                            let mut tasks_guard = tasks_arc.lock().unwrap();
+                            (tasks_guard.pop(), tasks_guard.len())
+                        };
+                        if let Some((i, j)) = task {
+                            let mut pixel_color = Color::default();
+                            aov_sums.fill(Color::default());
+                            for _ in 0..samples_per_pixel {
+                                let u = (f64::from(i) + random_f64_01()) / f64::from(image_width);
+                                let v = (f64::from(j) + random_f64_01()) / f64::from(image_height);
+                                match camera_arc.get_ray(u, v) {
+                                    Some((weight, r)) if aov_list.is_empty() => {
+                                        stats::primary_ray();
+                                        pixel_color += integrator_arc.li_splat(&r, &world_arc, &mut splats) * weight
+                                    }
+                                    Some((weight, r)) => {
+                                        stats::primary_ray();
+                                        let mut aovs = Aovs::default();
+                                        pixel_color += integrator_arc.li_aovs(&r, &world_arc, &mut splats, &mut aovs) * weight;
+                                        for (sum, &aov) in aov_sums.iter_mut().zip(&aov_list) {
+                                            *sum += if aov.is_lighting() { aovs.get(aov) * weight } else { aovs.get(aov) };
+                                        }
+                                    }
+                                    None => integrator_arc.splat_only(&world_arc, &mut splats),
+                                }
+                            }
+
+                            let mut framebuffer = framebuffer_arc.lock().unwrap();
+                            let (x, y) = (i as usize, (image_height - 1 - j) as usize);
+                            framebuffer.add_samples(x, y, pixel_color, samples_per_pixel as u32);
+                            for (sum, aov) in aov_sums.iter().zip(&aov_list) {
+                                let channel = framebuffer.channel_mut(aov.name()).expect("channel for every AOV");
+                                channel.set(x, y, channel.get(x, y) + *sum);
+                            }
+                            for splat in splats.drain(..) {
+                                let x = ((splat.s * f64::from(image_width)) as i32).min(image_width - 1);
+                                let y = ((splat.t * f64::from(image_height)) as i32).min(image_height - 1);
+                                framebuffer.add_splat(x as usize, (image_height - 1 - y) as usize, splat.color);
+                            }
+                            framebuffer.stats += stats::take();
+                            let rays = framebuffer.stats.total_rays();
+                            drop(framebuffer);
+
+                            // a few times a second, by whichever thread gets there first
+                            if progress {
+                                if let Ok(mut last) = last_report_arc.try_lock() {
+                                    if last.elapsed() >= Duration::from_millis(250) {
+                                        *last = Instant::now();
+                                        let done = done_before + pixel_count - remaining;
+                                        report(done, *task_count_arc, rays, start.elapsed().as_secs_f64());
+                                    }
+                                }
+                            }
+                        } else {
+                            break
+                        }
+                    }
+                });
+                thread_handlers.push(render_handler);
+            }
+
+            for handler in thread_handlers {
+                handler.join().unwrap();
+            }
+        }
+
+        let mut framebuffer = Arc::try_unwrap(framebuffer_mutex).expect("render threads finished").into_inner().unwrap();
+        framebuffer.seconds = start.elapsed().as_secs_f64();
+        if self.progress {
+            report(*task_count, *task_count, framebuffer.stats.total_rays(), framebuffer.seconds);
+            eprintln!("\n{}", framebuffer.stats.summary(framebuffer.seconds));
+        }
+        framebuffer
+    }
+}
+
+// Minutes and seconds, with hours in front once there are any.
+fn clock (seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+    }
+}
+
+// One progress line on stderr, written over the last. The time left assumes the
+// pixels still to go cost what the finished ones did.
+fn report (done: usize, total: usize, rays: u64, seconds: f64) {
+    let fraction = done as f64 / total.max(1) as f64;
+    let left = if done > 0 { clock(seconds * (1.0 - fraction) / fraction) } else { String::from("?") };
+    eprint!(
+        "\r{:5.1}%  {} elapsed  {} left  {:.2}M rays/s   ",
+        100.0 * fraction,
+        clock(seconds),
+        left,
+        if seconds > 0.0 { rays as f64 / seconds / 1e6 } else { 0.0 },
+    );
+}
@@ -0,0 +1,719 @@
+// The scenes the command line renders, as examples of building worlds in code.
+
+use std::sync::Arc;
+
+use crate::common::vec3::{Vec3, Color, Point3};
+use crate::common::vec3_opts::unit_vector;
+use crate::common::keyframe::{Interpolation, Key, Track};
+use crate::one_week::cutout::Cutout;
+use crate::one_week::hittable::Hittable;
+use crate::one_week::hittable_list::HittableList;
+use crate::one_week::layered::{Coated, MixMaterial};
+use crate::one_week::light::{DirectionalLight, IesProfile, LightList, PointLight, SpotLight};
+use crate::one_week::material::{Lambertian, Metal, Dielectric};
+use crate::one_week::medium::{Dispersion, Medium};
+use crate::one_week::scene::{Background, Scene};
+use crate::one_week::{random_f64, random_f64_01};
+use crate::one_week::sphere::Sphere;
+use crate::one_week::subsurface::Subsurface;
+use crate::one_week::normal_map::NormalMapped;
+use crate::one_week::texture::{ImageTexture, NoiseTexture, SolidColor};
+use crate::one_week::thin_film::{Substrate, ThinFilm};
+use crate::one_week::transform::Animated;
+use crate::one_week::aabb::Aabb;
+use crate::one_week::perlin::Perlin;
+use crate::one_week::volume::{DensityField, HeterogeneousMedium, NoiseDensity, VoxelGrid};
+
+// The final scene of the first book: a field of small random spheres around three
+// big ones, lit by the sky.
+pub fn random_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    // ground material
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian {
+            albedo: Color::new(0.5, 0.5, 0.5)
+        })
+    )));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random_f64_01();
+            let center = Point3::new(
+                f64::from(a) + 0.9 * random_f64_01(),
+                0.2,
+                f64::from(b) + 0.9 * random_f64_01()
+            );
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                if choose_mat < 0.8 {
+                    let albedo = Color::random_01() * Color::random_01();
+                    world.add(Arc::new(Sphere::new(
+                        center, 0.2, Box::new(Lambertian { albedo })
+                    )));
+                } else if choose_mat < 0.95 {
+                    let albedo = Color::random(0.5, 1.0);
+                    let fuzz = random_f64(0.0, 0.5);
+                    world.add(Arc::new(Sphere::new(
+                        center, 0.2, Box::new(Metal::new(albedo, fuzz))
+                    )));
+                } else {
+                    world.add(Arc::new(Sphere::new(
+                        center, 0.2, Box::new(Dielectric::new(1.5))
+                    )));
+                }
+            }
+        }
+    }
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Dielectric::new(1.5))
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Lambertian { albedo: Color::new(0.4, 0.2, 0.1) })
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0))
+    )));
+
+    Scene::new(world, LightList { lights: Vec::new() }, Background::Sky)
+}
+
+pub fn lights_scene (ies: Option<IesProfile>) -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Lambertian { albedo: Color::new(0.4, 0.2, 0.1) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Lambertian { albedo: Color::new(0.2, 0.4, 0.7) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0))
+    )));
+
+    let mut lights = LightList { lights: Vec::new() };
+
+    let mut lamp = PointLight::new(Point3::new(-4.0, 4.0, 2.0), Color::new(30.0, 24.0, 16.0));
+    if let Some(profile) = ies {
+        lamp = lamp.with_profile(Arc::new(profile), Vec3::new(0.0, -1.0, 0.0));
+    }
+    lights.add(Arc::new(lamp));
+    lights.add(Arc::new(SpotLight::new(
+        Point3::new(3.0, 6.0, 4.0),
+        Point3::new(0.0, 1.0, 0.0),
+        Color::new(60.0, 60.0, 60.0),
+        25.0,
+        15.0,
+    )));
+    lights.add(Arc::new(DirectionalLight::new(
+        Vec3::new(-1.0, -1.0, -0.5),
+        Color::new(0.15, 0.15, 0.25),
+    )));
+
+    Scene::new(world, lights, Background::Solid(Color::new(0.02, 0.02, 0.03)))
+}
+
+// A glass sphere under a point light: the caustic it throws is only reachable from the light.
+pub fn caustics_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.6, 0.6, 0.6) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Dielectric::new(1.5))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-3.0, 0.7, -2.0),
+        0.7,
+        Box::new(Lambertian { albedo: Color::new(0.7, 0.3, 0.2) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(2.0, 0.5, -3.0),
+        0.5,
+        Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0))
+    )));
+
+    let mut lights = LightList { lights: Vec::new() };
+    lights.add(Arc::new(PointLight::new(Point3::new(-1.0, 5.0, 1.0), Color::new(40.0, 40.0, 40.0))));
+
+    Scene::new(world, lights, Background::Solid(Color::default()))
+}
+
+// Tinted glass spheres of different sizes next to a glass of water, lit by the sky.
+// The big tinted sphere comes out darker than the small one made of the same glass.
+pub fn glass_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+
+    let tinted = Arc::new(Medium::new(1.5, Color::new(0.9, 0.15, 0.6)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Dielectric::with_media(tinted.clone(), None))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(3.0, 0.3, 1.8),
+        0.3,
+        Box::new(Dielectric::with_media(tinted, None))
+    )));
+
+    // The water surface sits inside the glass, so it refracts from 1.5 to 1.33.
+    let glass = Arc::new(Medium::new(1.5, Color::default()));
+    let water = Arc::new(Medium::new(1.33, Color::new(0.5, 0.12, 0.03)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Dielectric::with_media(glass.clone(), None))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        0.9,
+        Box::new(Dielectric::with_media(water, Some(glass)))
+    )));
+
+    Scene::new(world, LightList { lights: Vec::new() }, Background::Sky)
+}
+
+// Dispersive glass in front of a row of coloured balls; render with the spectral
+// integrator to see the colour fringes.
+pub fn dispersion_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+
+    // Schott SF11 dense flint.
+    let flint = Arc::new(Medium::dispersive(
+        Dispersion::Sellmeier {
+            b: [1.737_596_95, 0.313_747_346, 1.898_781_01],
+            c: [0.013_188_707, 0.062_306_814_2, 155.236_29],
+        },
+        Color::default(),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Dielectric::with_media(flint, None))
+    )));
+    // Far more dispersive than any real glass.
+    let cauchy = Arc::new(Medium::dispersive(Dispersion::Cauchy { a: 1.45, b: 0.05 }, Color::default()));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(3.0, 0.6, 1.6),
+        0.6,
+        Box::new(Dielectric::with_media(cauchy, None))
+    )));
+
+    for i in 0..7 {
+        let z = f64::from(i) - 3.0;
+        let hue = f64::from(i) / 7.0;
+        let albedo = Color::new(
+            0.5 + 0.5 * (2.0 * std::f64::consts::PI * hue).cos(),
+            0.5 + 0.5 * (2.0 * std::f64::consts::PI * (hue - 1.0 / 3.0)).cos(),
+            0.5 + 0.5 * (2.0 * std::f64::consts::PI * (hue - 2.0 / 3.0)).cos(),
+        );
+        world.add(Arc::new(Sphere::new(
+            Point3::new(-4.0, 0.5, z),
+            0.5,
+            Box::new(Lambertian { albedo })
+        )));
+    }
+
+    Scene::new(world, LightList { lights: Vec::new() }, Background::Sky)
+}
+
+// A soap bubble, an iridescent metal ball and a coated glass ball under the sky.
+pub fn thin_film_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+
+    // Soap film between air outside and air inside, swirling from 200 to 900 nm.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(ThinFilm {
+            substrate: Substrate::Dielectric(Dielectric::new(1.0)),
+            thickness: Arc::new(NoiseTexture {
+                noise: Perlin::new(),
+                scale: 2.0,
+                low: Color::new(200.0, 200.0, 200.0),
+                high: Color::new(900.0, 900.0, 900.0),
+            }),
+            ior: Arc::new(SolidColor::scalar(1.33)),
+        })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(ThinFilm {
+            substrate: Substrate::Metal(Metal::new(Color::new(0.6, 0.6, 0.6), 0.05)),
+            thickness: Arc::new(NoiseTexture {
+                noise: Perlin::new(),
+                scale: 4.0,
+                low: Color::new(300.0, 300.0, 300.0),
+                high: Color::new(600.0, 600.0, 600.0),
+            }),
+            ior: Arc::new(SolidColor::scalar(1.5)),
+        })
+    )));
+    // Quarter wave magnesium fluoride, the classic anti-reflection coating.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(ThinFilm {
+            substrate: Substrate::Dielectric(Dielectric::new(1.5)),
+            thickness: Arc::new(SolidColor::scalar(100.0)),
+            ior: Arc::new(SolidColor::scalar(1.38)),
+        })
+    )));
+
+    Scene::new(world, LightList { lights: Vec::new() }, Background::Sky)
+}
+
+// Marble, skin and wax next to a Lambertian ball of the same colour as the wax,
+// under a point light.
+pub fn subsurface_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Subsurface::new(Color::new(0.99, 0.99, 0.98), Color::new(0.08, 0.08, 0.08), 1.5, 0.0))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Subsurface::new(Color::new(0.97, 0.8, 0.7), Color::new(0.35, 0.12, 0.06), 1.4, 0.8))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Subsurface::new(Color::new(0.98, 0.9, 0.6), Color::new(0.25, 0.2, 0.1), 1.45, 0.0))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(3.0, 0.4, 2.2),
+        0.4,
+        Box::new(Lambertian { albedo: Color::new(0.98, 0.9, 0.6) })
+    )));
+
+    let mut lights = LightList { lights: Vec::new() };
+    lights.add(Arc::new(PointLight::new(Point3::new(-2.0, 5.0, -3.0), Color::new(50.0, 48.0, 45.0))));
+
+    Scene::new(world, lights, Background::Solid(Color::new(0.05, 0.06, 0.08)))
+}
+
+// Two colonnades of stacked spheres under a midday sun, lit in physical units so
+// it needs exposing like a real photograph.
+pub fn daylight_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.45, 0.42, 0.38) })
+    )));
+    for column in 0..6 {
+        for side in [-2.0, 2.0] {
+            for level in 0..5 {
+                world.add(Arc::new(Sphere::new(
+                    Point3::new(6.0 - 3.0 * f64::from(column), 0.5 + f64::from(level), side),
+                    0.5,
+                    Box::new(Lambertian { albedo: Color::new(0.8, 0.78, 0.72) })
+                )));
+            }
+        }
+    }
+
+    let mut lights = LightList { lights: Vec::new() };
+    // about 100,000 lux of sun, and a sky of some thousands of cd/m²
+    lights.add(Arc::new(DirectionalLight::new(Vec3::new(-0.4, -1.0, -0.3), Color::new(1.0e5, 0.97e5, 0.92e5))));
+    Scene::new(world, lights, Background::Solid(Color::new(4000.0, 5500.0, 8000.0)))
+}
+
+// Clear-coated red plastic, blue car paint, and brushed metal rusting through
+// under a noise mask, lit by the sky and a point light.
+pub fn layered_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Coated::new(
+            Arc::new(Lambertian { albedo: Color::new(0.7, 0.08, 0.05) }),
+            1.5,
+            0.0,
+            Color::new(1.0, 1.0, 1.0),
+        ))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Coated::new(
+            Arc::new(Metal::new(Color::new(0.1, 0.25, 0.7), 0.4)),
+            1.5,
+            0.02,
+            Color::new(0.95, 0.97, 1.0),
+        ))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(MixMaterial::with_mask(
+            Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.15)),
+            Arc::new(Lambertian { albedo: Color::new(0.45, 0.2, 0.08) }),
+            Arc::new(NoiseTexture {
+                noise: Perlin::new(),
+                scale: 3.0,
+                low: Color::new(0.0, 0.0, 0.0),
+                high: Color::new(1.0, 1.0, 1.0),
+            }),
+        ))
+    )));
+
+    // Glass with a milky tint, a quarter of the paths scatter diffusely.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(2.5, 0.4, 2.2),
+        0.4,
+        Box::new(MixMaterial::new(
+            Arc::new(Dielectric::new(1.5)),
+            Arc::new(Lambertian { albedo: Color::new(0.9, 0.9, 0.9) }),
+            0.25,
+        ))
+    )));
+
+    let mut lights = LightList { lights: Vec::new() };
+    lights.add(Arc::new(PointLight::new(Point3::new(-2.0, 5.0, 4.0), Color::new(30.0, 30.0, 30.0))));
+
+    Scene::new(world, lights, Background::Sky)
+}
+
+// Tangent-space normal map of round dimples in a square grid, `cells` of them around
+// a sphere's equator and half as many from pole to pole.
+fn dimples (cells: usize) -> ImageTexture {
+    const CELL: usize = 16;
+    let (width, height) = (cells * CELL, cells * CELL / 2);
+    let mut pixels = Vec::with_capacity(width * height);
+    for j in 0..height {
+        for i in 0..width {
+            // Offset from the dimple's centre, y up, in units of its radius.
+            let x = ((i % CELL) as f64 + 0.5 - CELL as f64 / 2.0) / (CELL as f64 * 0.45);
+            let y = -((j % CELL) as f64 + 0.5 - CELL as f64 / 2.0) / (CELL as f64 * 0.45);
+            let normal = if x * x + y * y < 1.0 {
+                unit_vector(&Vec3::new(-x, -y, 1.2))
+            } else {
+                Vec3::new(0.0, 0.0, 1.0)
+            };
+            pixels.push((normal + Color::new(1.0, 1.0, 1.0)) * 0.5);
+        }
+    }
+    ImageTexture::new(width, height, pixels).expect("at least one dimple")
+}
+
+// Normal mapped metal, bumpy plaster and hammered glass. The metal takes its normal
+// map from `normal_map` when given, golf ball dimples otherwise.
+pub fn bumps_scene (normal_map: Option<ImageTexture>) -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(NormalMapped::bump(
+            Arc::new(Lambertian { albedo: Color::new(0.8, 0.75, 0.65) }),
+            Arc::new(NoiseTexture {
+                noise: Perlin::new(),
+                scale: 6.0,
+                low: Color::new(0.0, 0.0, 0.0),
+                high: Color::new(1.0, 1.0, 1.0),
+            }),
+            0.05,
+        ))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(NormalMapped::bump(
+            Arc::new(Dielectric::new(1.5)),
+            Arc::new(NoiseTexture {
+                noise: Perlin::new(),
+                scale: 12.0,
+                low: Color::new(0.0, 0.0, 0.0),
+                high: Color::new(1.0, 1.0, 1.0),
+            }),
+            0.01,
+        ))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(NormalMapped::normal_map(
+            Arc::new(Metal::new(Color::new(0.8, 0.8, 0.75), 0.05)),
+            Arc::new(normal_map.unwrap_or_else(|| dimples(24))),
+        ))
+    )));
+
+    let mut lights = LightList { lights: Vec::new() };
+    lights.add(Arc::new(PointLight::new(Point3::new(-2.0, 5.0, 4.0), Color::new(30.0, 30.0, 30.0))));
+
+    Scene::new(world, lights, Background::Sky)
+}
+
+// Opacity mask of square holes in a grid, `cells` of them around a sphere's equator,
+// leaving bars `bar` of a cell wide.
+fn lattice (cells: usize, bar: f64) -> ImageTexture {
+    const CELL: usize = 16;
+    let (width, height) = (cells * CELL, cells * CELL / 2);
+    let mut pixels = Vec::with_capacity(width * height);
+    for j in 0..height {
+        for i in 0..width {
+            let x = ((i % CELL) as f64 + 0.5) / CELL as f64;
+            let y = ((j % CELL) as f64 + 0.5) / CELL as f64;
+            let solid = x < bar || y < bar;
+            pixels.push(if solid { Color::new(1.0, 1.0, 1.0) } else { Color::default() });
+        }
+    }
+    ImageTexture::new(width, height, pixels).expect("at least one hole")
+}
+
+// A lattice ball with a red ball inside, a marble-veined cutout and a faint ghost,
+// under a point light so the holes show in the shadows.
+pub fn cutout_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Cutout::new(
+            Arc::new(Lambertian { albedo: Color::new(0.8, 0.6, 0.2) }),
+            Arc::new(lattice(12, 0.3)),
+        ))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        0.4,
+        Box::new(Lambertian { albedo: Color::new(0.7, 0.1, 0.1) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Cutout::new(
+            Arc::new(Lambertian { albedo: Color::new(0.2, 0.5, 0.2) }),
+            Arc::new(NoiseTexture {
+                noise: Perlin::new(),
+                scale: 4.0,
+                low: Color::new(-1.0, -1.0, -1.0),
+                high: Color::new(2.0, 2.0, 2.0),
+            }),
+        ))
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Cutout::new(
+            Arc::new(Metal::new(Color::new(0.8, 0.85, 0.9), 0.1)),
+            Arc::new(SolidColor::scalar(0.35)),
+        ))
+    )));
+
+    let mut lights = LightList { lights: Vec::new() };
+    lights.add(Arc::new(PointLight::new(Point3::new(2.0, 6.0, 3.0), Color::new(40.0, 40.0, 40.0))));
+
+    Scene::new(world, lights, Background::Sky)
+}
+
+// A cloud over a matte ground, lit by the sun and the sky. The density comes from
+// `grid` when given, from turbulent noise otherwise.
+pub fn smoke_scene (grid: Option<VoxelGrid>) -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+
+    let density: Arc<dyn DensityField> = match grid {
+        Some(grid) => Arc::new(grid),
+        None => Arc::new(NoiseDensity {
+            perlin: Perlin::new(),
+            bounds: smoke_bounds(),
+            frequency: 1.5,
+            octaves: 5,
+        }),
+    };
+    world.add(Arc::new(HeterogeneousMedium::new(
+        density,
+        8.0,
+        Color::new(0.9, 0.9, 0.9),
+        0.6,
+    )));
+
+    let mut lights = LightList { lights: Vec::new() };
+    lights.add(Arc::new(DirectionalLight::new(
+        Vec3::new(-1.0, -1.5, 0.5),
+        Color::new(1.2, 1.1, 1.0),
+    )));
+
+    Scene::new(world, lights, Background::Sky)
+}
+
+pub fn smoke_bounds () -> Aabb {
+    Aabb::new(Point3::new(-2.5, 0.0, -2.5), Point3::new(2.5, 2.2, 2.5))
+}
+
+// A ball bouncing on sharp Bezier handles, one sliding along linearly, and a spinning,
+// breathing dumbbell weaving along a Catmull-Rom path, over two seconds.
+pub fn animated_scene () -> Scene {
+    let mut world = HittableList {
+        objects: Vec::<Arc<dyn Hittable>>::new()
+    };
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) })
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0))
+    )));
+
+    let mut bouncing = Animated::new(Arc::new(Sphere::new(
+        Point3::new(0.0, 0.0, 0.0),
+        0.7,
+        Box::new(Lambertian { albedo: Color::new(0.8, 0.2, 0.1) })
+    )));
+    let (top, floor) = (Vec3::new(2.0, 3.2, 2.0), Vec3::new(2.0, 0.7, 2.0));
+    let lift = Vec3::new(0.0, 1.5, 0.0);
+    bouncing.translation = Track::new(Interpolation::Bezier, vec![
+        Key::new(0.0, top),
+        Key::with_handles(0.5, floor, floor + lift, floor + lift),
+        Key::new(1.0, top),
+        Key::with_handles(1.5, floor, floor + lift, floor + lift),
+        Key::new(2.0, top),
+    ]);
+
+    let mut sliding = Animated::new(Arc::new(Sphere::new(
+        Point3::new(0.0, 0.0, 0.0),
+        0.5,
+        Box::new(Dielectric::new(1.5))
+    )));
+    sliding.translation = Track::new(Interpolation::Linear, vec![
+        Key::new(0.0, Vec3::new(4.0, 0.5, -3.0)),
+        Key::new(2.0, Vec3::new(-2.0, 0.5, -3.0)),
+    ]);
+
+    let mut dumbbell = HittableList { objects: Vec::new() };
+    for end in [-0.8, 0.8] {
+        dumbbell.add(Arc::new(Sphere::new(
+            Point3::new(end, 0.0, 0.0),
+            0.4,
+            Box::new(Lambertian { albedo: Color::new(0.1, 0.3, 0.8) })
+        )));
+    }
+    let mut weaving = Animated::new(Arc::new(dumbbell));
+    weaving.translation = Track::new(Interpolation::CatmullRom, vec![
+        Key::new(0.0, Vec3::new(0.0, 0.5, -1.5)),
+        Key::new(0.5, Vec3::new(1.0, 0.5, 0.0)),
+        Key::new(1.0, Vec3::new(0.0, 0.5, 1.5)),
+        Key::new(1.5, Vec3::new(-1.0, 0.5, 0.0)),
+        Key::new(2.0, Vec3::new(0.0, 0.5, -1.5)),
+    ]);
+    weaving.rotation = Track::new(Interpolation::Linear, vec![
+        Key::new(0.0, Vec3::new(0.0, 0.0, 0.0)),
+        Key::new(2.0, Vec3::new(0.0, 720.0, 0.0)),
+    ]);
+    weaving.scale = Track::new(Interpolation::CatmullRom, vec![
+        Key::new(0.0, 1.0),
+        Key::new(0.5, 1.3),
+        Key::new(1.0, 1.0),
+        Key::new(1.5, 1.3),
+        Key::new(2.0, 1.0),
+    ]);
+
+    let mut lights = LightList { lights: Vec::new() };
+    lights.add(Arc::new(PointLight::new(Point3::new(3.0, 8.0, 4.0), Color::new(60.0, 60.0, 60.0))));
+    Scene::new(world, lights, Background::Sky).with_animated(vec![bouncing, sliding, weaving])
+}